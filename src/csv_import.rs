@@ -0,0 +1,570 @@
+//! Building a project from CSV tables for tasks, resources and allocations.
+//!
+//! The columns are named like the fields of the JSON project format and may
+//! appear in any order. Files exported from spreadsheets that use `;` as
+//! separator are detected by their header line.
+
+use std::{collections::HashMap, fmt};
+
+use time::{Date, Month};
+
+use crate::pom::{
+    validate_allocations, validate_tasks, Allocation, Project, ProjectError, Resource, RoleDemand,
+    Task,
+};
+
+/// A CSV file and the name it is reported under in errors.
+pub struct CsvFile {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Debug)]
+pub struct CsvError {
+    pub file: String,
+    pub line: u64,
+    /// 1-based column number and its header
+    pub column: Option<(usize, String)>,
+    pub message: String,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.column {
+            Some((number, header)) => write!(
+                f,
+                "{}, line {}, column {} ({}): {}",
+                self.file, self.line, number, header, self.message
+            ),
+            None => write!(f, "{}, line {}: {}", self.file, self.line, self.message),
+        }
+    }
+}
+
+/// Parses dates written as 2023-06-08 or 08.06.2023.
+fn parse_date(text: &str) -> Option<Date> {
+    let parts: Vec<&str> = if text.contains('.') {
+        text.split('.').rev().collect()
+    } else {
+        text.split('-').collect()
+    };
+    let [year, month, day] = parts[..] else {
+        return None;
+    };
+    let month = Month::try_from(month.parse::<u8>().ok()?).ok()?;
+    Date::from_calendar_date(year.parse().ok()?, month, day.parse().ok()?).ok()
+}
+
+/// Parses numbers with either a decimal point or a decimal comma.
+fn parse_f32(text: &str) -> Option<f32> {
+    text.replace(',', ".").parse().ok()
+}
+
+/// Parses predecessor lists like "3;7". An empty cell means no predecessors.
+fn parse_id_list(text: &str) -> Option<Vec<u32>> {
+    text.split([';', ','])
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse().ok())
+        .collect()
+}
+
+/// Parses tag lists like "backend;team a".
+fn parse_tag_list(text: &str) -> Vec<String> {
+    text.split(';')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Parses the FTE needed per role, like "backend=1.5;qa=0.5".
+fn parse_role_demand(text: &str) -> Option<Vec<RoleDemand>> {
+    text.split(';')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            let (role, fte) = x.split_once('=')?;
+            let role = role.trim();
+            (!role.is_empty()).then_some(())?;
+            Some(RoleDemand {
+                role: role.to_string(),
+                fte: parse_f32(fte.trim())?,
+            })
+        })
+        .collect()
+}
+
+struct Table<'a> {
+    file: &'a CsvFile,
+    headers: HashMap<String, usize>,
+    header_names: Vec<String>,
+    records: Vec<csv::StringRecord>,
+}
+
+impl<'a> Table<'a> {
+    fn read(file: &'a CsvFile, required_columns: &[&str]) -> Result<Table<'a>, CsvError> {
+        let first_line = file.content.lines().next().unwrap_or("");
+        let delimiter = if first_line.contains(';') && !first_line.contains(',') {
+            b';'
+        } else {
+            b','
+        };
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .trim(csv::Trim::All)
+            .from_reader(file.content.as_bytes());
+
+        let to_error = |e: csv::Error| CsvError {
+            file: file.name.clone(),
+            line: e.position().map(|x| x.line()).unwrap_or(1),
+            column: None,
+            message: e.to_string(),
+        };
+
+        let header_names: Vec<String> = reader
+            .headers()
+            .map_err(to_error)?
+            .iter()
+            .map(|x| x.to_lowercase())
+            .collect();
+        let headers: HashMap<String, usize> = header_names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), index))
+            .collect();
+        for column in required_columns {
+            if !headers.contains_key(*column) {
+                return Err(CsvError {
+                    file: file.name.clone(),
+                    line: 1,
+                    column: None,
+                    message: format!("Missing column '{}'", column),
+                });
+            }
+        }
+
+        let records = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(to_error)?;
+
+        Ok(Table {
+            file,
+            headers,
+            header_names,
+            records,
+        })
+    }
+
+    fn error_at(&self, record: &csv::StringRecord, column: &str, message: String) -> CsvError {
+        let index = self.headers[column];
+        CsvError {
+            file: self.file.name.clone(),
+            line: record.position().map(|x| x.line()).unwrap_or(0),
+            column: Some((index + 1, self.header_names[index].clone())),
+            message,
+        }
+    }
+
+    /// Returns the cell or None if the column does not exist in this file.
+    fn optional_cell<'r>(&self, record: &'r csv::StringRecord, column: &str) -> Option<&'r str> {
+        self.headers
+            .get(column)
+            .and_then(|index| record.get(*index))
+    }
+
+    fn cell<T>(
+        &self,
+        record: &csv::StringRecord,
+        column: &str,
+        what: &str,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<T, CsvError> {
+        let text = self.optional_cell(record, column).unwrap_or("");
+        parse(text).ok_or_else(|| {
+            let message = if text.is_empty() {
+                format!("Missing {}", what)
+            } else {
+                format!("'{}' is not {}", text, what)
+            };
+            self.error_at(record, column, message)
+        })
+    }
+}
+
+fn read_tasks(file: &CsvFile) -> Result<Vec<Task>, CsvError> {
+    let table = Table::read(
+        file,
+        &[
+            "id",
+            "duration",
+            "label",
+            "earliest_start_date",
+            "planned_resources",
+        ],
+    )?;
+    let mut tasks = vec![];
+    for record in table.records.iter() {
+        let id = table.cell(record, "id", "a task id", |x| x.parse().ok())?;
+        if tasks.iter().any(|x: &Task| x.id == id) {
+            return Err(table.error_at(record, "id", format!("Task id {} is used twice", id)));
+        }
+        let predecessors = match table.optional_cell(record, "predecessors") {
+            Some(_) => table.cell(
+                record,
+                "predecessors",
+                "a list of task ids like 3;7",
+                parse_id_list,
+            )?,
+            None => vec![],
+        };
+        tasks.push(Task {
+            id,
+            duration: table.cell(record, "duration", "a duration in hours", |x| {
+                x.parse().ok()
+            })?,
+            label: table
+                .optional_cell(record, "label")
+                .unwrap_or("")
+                .to_string(),
+            earliest_start_date: table.cell(
+                record,
+                "earliest_start_date",
+                "a date like 2023-06-08 or 08.06.2023",
+                parse_date,
+            )?,
+            planned_resources: table.cell(record, "planned_resources", "a number", parse_f32)?,
+            allocated_resources: 0.0,
+            predecessors,
+            tags: table
+                .optional_cell(record, "tags")
+                .map_or(vec![], parse_tag_list),
+            color: table
+                .optional_cell(record, "color")
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string()),
+            role_demand: match table.optional_cell(record, "role_demand") {
+                Some(_) => table.cell(
+                    record,
+                    "role_demand",
+                    "a list of roles and FTE like backend=1.5;qa=0.5",
+                    parse_role_demand,
+                )?,
+                None => vec![],
+            },
+        });
+    }
+
+    // Only now all ids are known, so check the predecessors in a second pass.
+    for (task, record) in tasks.iter().zip(table.records.iter()) {
+        if let Some(unknown) = task
+            .predecessors
+            .iter()
+            .find(|pred_id| !tasks.iter().any(|x| x.id == **pred_id))
+        {
+            return Err(table.error_at(
+                record,
+                "predecessors",
+                format!("There is no task with id {}", unknown),
+            ));
+        }
+    }
+    // Report what pom rejects at the task's cell, records and tasks match.
+    validate_tasks(&tasks).map_err(|e| {
+        let (id, column) = match e {
+            ProjectError::PlannedResources(id) => (id, "planned_resources"),
+            ProjectError::OwnPredecessor(id) | ProjectError::PredecessorCycle(id) => {
+                (id, "predecessors")
+            }
+            ProjectError::NegativeLoad { .. } => unreachable!("tasks have no loads"),
+        };
+        let index = tasks.iter().position(|x| x.id == id).unwrap();
+        table.error_at(&table.records[index], column, e.to_string())
+    })?;
+    Ok(tasks)
+}
+
+fn read_resources(file: &CsvFile) -> Result<Vec<Resource>, CsvError> {
+    let table = Table::read(file, &["id", "label", "output"])?;
+    let mut resources = vec![];
+    for record in table.records.iter() {
+        let id = table.cell(record, "id", "a resource id", |x| x.parse().ok())?;
+        if resources.iter().any(|x: &Resource| x.id == id) {
+            return Err(table.error_at(record, "id", format!("Resource id {} is used twice", id)));
+        }
+        resources.push(Resource {
+            id,
+            label: table
+                .optional_cell(record, "label")
+                .unwrap_or("")
+                .to_string(),
+            output: table.cell(record, "output", "a number of hours", parse_f32)?,
+            team: table
+                .optional_cell(record, "team")
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string()),
+            roles: table
+                .optional_cell(record, "roles")
+                .map_or(vec![], parse_tag_list),
+        });
+    }
+    Ok(resources)
+}
+
+fn read_allocations(
+    file: &CsvFile,
+    tasks: &[Task],
+    resources: &[Resource],
+) -> Result<Vec<Allocation>, CsvError> {
+    let table = Table::read(file, &["taskid", "resourceid", "load"])?;
+    let mut allocations = vec![];
+    for record in table.records.iter() {
+        let taskid = table.cell(record, "taskid", "a task id", |x| x.parse().ok())?;
+        if !tasks.iter().any(|x| x.id == taskid) {
+            return Err(table.error_at(
+                record,
+                "taskid",
+                format!("There is no task with id {}", taskid),
+            ));
+        }
+        let resourceid = table.cell(record, "resourceid", "a resource id", |x| x.parse().ok())?;
+        if !resources.iter().any(|x| x.id == resourceid) {
+            return Err(table.error_at(
+                record,
+                "resourceid",
+                format!("There is no resource with id {}", resourceid),
+            ));
+        }
+        allocations.push(Allocation {
+            taskid,
+            resourceid,
+            load: table.cell(record, "load", "a number", parse_f32)?,
+        });
+    }
+    validate_allocations(&allocations).map_err(|e| match e {
+        ProjectError::NegativeLoad { index, .. } => {
+            table.error_at(&table.records[index], "load", e.to_string())
+        }
+        _ => unreachable!("allocations only have loads to check"),
+    })?;
+    Ok(allocations)
+}
+
+/// Builds a project from the three CSV tables.
+pub fn read_csv_project(
+    tasks: &CsvFile,
+    resources: &CsvFile,
+    allocations: &CsvFile,
+) -> Result<Project, CsvError> {
+    let tasks = read_tasks(tasks)?;
+    let resources = read_resources(resources)?;
+    let allocations = read_allocations(allocations, &tasks, &resources)?;
+    Ok(Project {
+        tasks,
+        resources,
+        allocations,
+        holidays: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::{parse_date, read_csv_project, CsvFile};
+
+    fn file(name: &str, content: &str) -> CsvFile {
+        CsvFile {
+            name: name.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    fn resources() -> CsvFile {
+        file(
+            "resources.csv",
+            "id,label,output,team,roles\n0,Hans,32,Platform,backend;qa\n1,Ernst,40,,\n",
+        )
+    }
+
+    fn allocations() -> CsvFile {
+        file(
+            "allocations.csv",
+            "taskid,resourceid,load\n0,0,1.0\n1,1,0.75\n",
+        )
+    }
+
+    #[test]
+    pub fn can_parse_dates() {
+        assert_eq!(parse_date("2023-06-08"), Some(date!(2023 - 06 - 08)));
+        assert_eq!(parse_date("08.06.2023"), Some(date!(2023 - 06 - 08)));
+        assert_eq!(parse_date("8.6.2023"), Some(date!(2023 - 06 - 08)));
+        assert_eq!(parse_date("31.02.2023"), None);
+        assert_eq!(parse_date("2023/06/08"), None);
+    }
+
+    #[test]
+    pub fn can_build_project() {
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date,predecessors,tags,color,role_demand\n\
+             0,Foo,240,2.0,2023-01-02,,,,\n\
+             1,\"Bar, Baz\",540,3,08.06.2023,0,backend; team a,#FF8800,\"backend=1.5; qa=0,5\"\n\
+             2,Qux,80,1,08.06.2023,\"0;1\",,,\n",
+        );
+        let p = read_csv_project(&tasks, &resources(), &allocations()).unwrap();
+
+        assert_eq!(p.tasks.len(), 3);
+        assert_eq!(p.tasks[1].label, "Bar, Baz");
+        assert_eq!(p.tasks[1].earliest_start_date, date!(2023 - 06 - 08));
+        assert_eq!(p.tasks[2].predecessors, vec![0, 1]);
+        assert_eq!(p.tasks[1].tags, vec!["backend", "team a"]);
+        assert_eq!(p.tasks[1].color.as_deref(), Some("#FF8800"));
+        assert!(p.tasks[0].tags.is_empty() && p.tasks[0].color.is_none());
+        assert_eq!(p.resources[0].output, 32.0);
+        assert_eq!(p.resources[0].team.as_deref(), Some("Platform"));
+        assert_eq!(p.resources[1].team, None);
+        assert_eq!(p.resources[0].roles, vec!["backend", "qa"]);
+        assert!(p.resources[1].roles.is_empty());
+        let demand: Vec<(&str, f32)> = p.tasks[1]
+            .role_demand
+            .iter()
+            .map(|x| (x.role.as_str(), x.fte))
+            .collect();
+        assert_eq!(demand, vec![("backend", 1.5), ("qa", 0.5)]);
+        assert!(p.tasks[0].role_demand.is_empty());
+        assert_eq!(p.allocations[1].load, 0.75);
+    }
+
+    #[test]
+    pub fn can_read_semicolon_separated_files() {
+        let tasks = file(
+            "tasks.csv",
+            "id;label;duration;planned_resources;earliest_start_date;predecessors\n\
+             0;Foo;240;1,5;02.01.2023;\n\
+             1;Bar;540;3;08.06.2023;\"0\"\n",
+        );
+        let p = read_csv_project(&tasks, &resources(), &allocations()).unwrap();
+
+        assert_eq!(p.tasks[0].planned_resources, 1.5);
+        assert_eq!(p.tasks[1].predecessors, vec![0]);
+    }
+
+    #[test]
+    pub fn reports_row_and_column_of_malformed_cells() {
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date\n\
+             0,Foo,240,2.0,2023-01-02\n\
+             1,Bar,540,3,31.06.2023\n",
+        );
+        let e = read_csv_project(&tasks, &resources(), &allocations()).unwrap_err();
+
+        assert_eq!(e.line, 3);
+        assert_eq!(e.column, Some((5, "earliest_start_date".to_string())));
+        assert_eq!(
+            e.to_string(),
+            "tasks.csv, line 3, column 5 (earliest_start_date): \
+             '31.06.2023' is not a date like 2023-06-08 or 08.06.2023"
+        );
+
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date,role_demand\n\
+             0,Foo,240,2.0,2023-01-02,backend\n",
+        );
+        let e = read_csv_project(&tasks, &resources(), &allocations()).unwrap_err();
+        assert_eq!(e.column, Some((6, "role_demand".to_string())));
+    }
+
+    #[test]
+    pub fn reports_unknown_references() {
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date,predecessors\n\
+             0,Foo,240,2.0,2023-01-02,7\n",
+        );
+        let e = read_csv_project(&tasks, &resources(), &allocations()).unwrap_err();
+        assert_eq!(e.line, 2);
+        assert_eq!(e.message, "There is no task with id 7");
+
+        let allocations = file("allocations.csv", "taskid,resourceid,load\n0,5,1.0\n");
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date\n0,Foo,240,2.0,2023-01-02\n",
+        );
+        let e = read_csv_project(&tasks, &resources(), &allocations).unwrap_err();
+        assert_eq!(e.column, Some((2, "resourceid".to_string())));
+    }
+
+    #[test]
+    pub fn reports_invalid_values() {
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date\n\
+             0,Foo,240,0,2023-01-02\n",
+        );
+        let e = read_csv_project(&tasks, &resources(), &allocations()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "tasks.csv, line 2, column 4 (planned_resources): \
+             Task 0 needs planned resources above 0"
+        );
+
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date\n\
+             0,Foo,240,2.0,2023-01-02\n\
+             1,Bar,540,3,08.06.2023\n",
+        );
+        let allocations = file(
+            "allocations.csv",
+            "taskid,resourceid,load\n0,0,1.0\n1,1,-0.5\n",
+        );
+        let e = read_csv_project(&tasks, &resources(), &allocations).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "allocations.csv, line 3, column 3 (load): \
+             The load of resource 1 on task 1 is negative"
+        );
+    }
+
+    #[test]
+    pub fn reports_predecessor_cycles() {
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date,predecessors\n\
+             0,Foo,240,2.0,2023-01-02,\n\
+             1,Bar,540,3,08.06.2023,1\n",
+        );
+        let e = read_csv_project(&tasks, &resources(), &allocations()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "tasks.csv, line 3, column 6 (predecessors): Task 1 is its own predecessor"
+        );
+
+        let tasks = file(
+            "tasks.csv",
+            "id,label,duration,planned_resources,earliest_start_date,predecessors\n\
+             0,Foo,240,2.0,2023-01-02,2\n\
+             1,Bar,540,3,08.06.2023,0\n\
+             2,Qux,80,1,08.06.2023,1\n",
+        );
+        let e = read_csv_project(&tasks, &resources(), &allocations()).unwrap_err();
+        assert_eq!(e.line, 2);
+        assert_eq!(e.column, Some((6, "predecessors".to_string())));
+        assert_eq!(e.message, "The predecessors of task 0 form a cycle");
+    }
+
+    #[test]
+    pub fn reports_missing_columns() {
+        let tasks = file("tasks.csv", "id,label\n0,Foo\n");
+        let e = read_csv_project(&tasks, &resources(), &allocations()).unwrap_err();
+
+        assert_eq!(
+            e.to_string(),
+            "tasks.csv, line 1: Missing column 'duration'"
+        );
+    }
+}
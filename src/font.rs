@@ -0,0 +1,75 @@
+//! The font embedded for drawing and measuring chart labels.
+
+use std::sync::OnceLock;
+
+use ttf_parser::Face;
+
+pub static FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+pub const FONT_FAMILY: &str = "DejaVu Sans";
+/// The font families of the SVG charts: the embedded font, then fonts of a
+/// similar width for viewers that do not have it.
+pub const FONT_FAMILIES: &str = "'DejaVu Sans', Verdana, sans-serif";
+
+const ELLIPSIS: char = '…';
+
+fn face() -> &'static Face<'static> {
+    static FACE: OnceLock<Face<'static>> = OnceLock::new();
+    FACE.get_or_init(|| Face::parse(FONT, 0).expect("the embedded font is valid"))
+}
+
+/// The width of the text in the embedded font, without kerning.
+pub fn text_width(text: &str, font_size: f32) -> f32 {
+    let face = face();
+    let units: u32 = text
+        .chars()
+        .map(|c| {
+            face.glyph_index(c)
+                .or_else(|| face.glyph_index('?'))
+                .and_then(|glyph| face.glyph_hor_advance(glyph))
+                .unwrap_or(0) as u32
+        })
+        .sum();
+    units as f32 * font_size / face.units_per_em() as f32
+}
+
+/// Shortens the text to fit the width, ending it with an ellipsis. Returns
+/// None if the text fits as it is.
+pub fn ellipsize(text: &str, font_size: f32, max_width: f32) -> Option<String> {
+    if text_width(text, font_size) <= max_width {
+        return None;
+    }
+    let mut shortened: String = text.to_string();
+    while !shortened.is_empty() {
+        shortened.pop();
+        let candidate = format!("{}{}", shortened.trim_end(), ELLIPSIS);
+        if text_width(&candidate, font_size) <= max_width {
+            return Some(candidate);
+        }
+    }
+    Some(ELLIPSIS.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ellipsize, text_width};
+
+    #[test]
+    pub fn measures_text_in_the_embedded_font() {
+        assert_eq!(text_width("", 8.0), 0.0);
+        assert!(text_width("MMMM", 8.0) > text_width("iiii", 8.0));
+        assert!((text_width("Task", 16.0) - 2.0 * text_width("Task", 8.0)).abs() < 0.01);
+        // DejaVu Sans averages a bit over half the font size per character.
+        let width = text_width("Another task, 90 days", 8.0);
+        assert!((80.0..110.0).contains(&width), "{}", width);
+    }
+
+    #[test]
+    pub fn shortens_text_with_an_ellipsis() {
+        assert_eq!(ellipsize("Sprint", 8.0, 100.0), None);
+        let short = ellipsize("A rather long task name", 8.0, 40.0).unwrap();
+        assert!(short.ends_with('…'));
+        assert!(text_width(&short, 8.0) <= 40.0);
+        assert!(short.len() > "…".len());
+        assert_eq!(ellipsize("Sprint", 8.0, 1.0).unwrap(), "…");
+    }
+}
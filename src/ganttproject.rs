@@ -0,0 +1,394 @@
+//! Importing GanttProject (.gan) files.
+//!
+//! GanttProject plans in working days while sgantt plans in hours of effort,
+//! so a task's duration is converted using the load of the resources
+//! allocated to it. Anything sgantt cannot represent is reported as a
+//! warning and left out instead of failing the import.
+
+use std::fmt;
+
+use time::{macros::format_description, Date, Month};
+
+use crate::pom::{Allocation, Project, ProjectError, Resource, Task, GENERIC_RESOURCE_OUTPUT};
+
+/// GanttProject's dependency type for "finish-start".
+const DEPENDENCY_FINISH_START: &str = "2";
+
+#[derive(Debug)]
+pub enum GanError {
+    Xml(roxmltree::Error),
+    NotAGanFile,
+    MissingAttribute {
+        element: String,
+        attribute: &'static str,
+    },
+    InvalidValue {
+        element: String,
+        attribute: &'static str,
+        value: String,
+    },
+    Invalid(ProjectError),
+}
+
+impl fmt::Display for GanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GanError::Xml(e) => write!(f, "Not a valid XML file: {}", e),
+            GanError::NotAGanFile => write!(f, "Not a GanttProject file"),
+            GanError::MissingAttribute { element, attribute } => {
+                write!(f, "Missing attribute '{}' on <{}>", attribute, element)
+            }
+            GanError::InvalidValue {
+                element,
+                attribute,
+                value,
+            } => write!(
+                f,
+                "Invalid value '{}' for attribute '{}' on <{}>",
+                value, attribute, element
+            ),
+            GanError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<roxmltree::Error> for GanError {
+    fn from(e: roxmltree::Error) -> Self {
+        GanError::Xml(e)
+    }
+}
+
+/// The result of an import: the project and everything that was left out.
+pub struct GanImport {
+    pub project: Project,
+    pub warnings: Vec<String>,
+}
+
+fn element_children<'a, 'input: 'a>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |x| x.is_element() && x.tag_name().name() == name)
+}
+
+fn required_attribute<'a>(
+    node: roxmltree::Node<'a, '_>,
+    attribute: &'static str,
+) -> Result<&'a str, GanError> {
+    node.attribute(attribute)
+        .ok_or_else(|| GanError::MissingAttribute {
+            element: node.tag_name().name().to_string(),
+            attribute,
+        })
+}
+
+fn parse_attribute<T: std::str::FromStr>(
+    node: roxmltree::Node,
+    attribute: &'static str,
+) -> Result<T, GanError> {
+    let value = required_attribute(node, attribute)?;
+    value.trim().parse().map_err(|_| GanError::InvalidValue {
+        element: node.tag_name().name().to_string(),
+        attribute,
+        value: value.to_string(),
+    })
+}
+
+fn parse_date_attribute(node: roxmltree::Node, attribute: &'static str) -> Result<Date, GanError> {
+    let value = required_attribute(node, attribute)?;
+    Date::parse(value, format_description!("[year]-[month]-[day]")).map_err(|_| {
+        GanError::InvalidValue {
+            element: node.tag_name().name().to_string(),
+            attribute,
+            value: value.to_string(),
+        }
+    })
+}
+
+/// A task as found in the file, before its duration can be converted.
+struct GanTask {
+    id: u32,
+    label: String,
+    start: Date,
+    days: f32,
+}
+
+/// Collects all leaf tasks and dependencies. Tasks containing other tasks are
+/// summary tasks in GanttProject, which sgantt has no notion of.
+fn collect_tasks(
+    node: roxmltree::Node,
+    tasks: &mut Vec<GanTask>,
+    dependencies: &mut Vec<(u32, u32)>,
+    warnings: &mut Vec<String>,
+) -> Result<(), GanError> {
+    for task in element_children(node, "task") {
+        let id: u32 = parse_attribute(task, "id")?;
+        let label = task.attribute("name").unwrap_or("").to_string();
+
+        for depend in element_children(task, "depend") {
+            let successor: u32 = parse_attribute(depend, "id")?;
+            if depend.attribute("type").unwrap_or(DEPENDENCY_FINISH_START)
+                != DEPENDENCY_FINISH_START
+            {
+                warnings.push(format!(
+                    "Dependency {} -> {} is not finish-to-start, imported as finish-to-start",
+                    id, successor
+                ));
+            }
+            if depend.attribute("difference").unwrap_or("0") != "0" {
+                warnings.push(format!(
+                    "Lag of dependency {} -> {} is not supported and was dropped",
+                    id, successor
+                ));
+            }
+            dependencies.push((id, successor));
+        }
+
+        if element_children(task, "task").next().is_some() {
+            warnings.push(format!(
+                "Summary task {} '{}' is not supported, only its subtasks were imported",
+                id, label
+            ));
+            collect_tasks(task, tasks, dependencies, warnings)?;
+            continue;
+        }
+
+        if task.attribute("meeting") == Some("true") {
+            warnings.push(format!(
+                "Milestone {} '{}' was imported as a task without duration",
+                id, label
+            ));
+        }
+
+        tasks.push(GanTask {
+            id,
+            label,
+            start: parse_date_attribute(task, "start")?,
+            days: parse_attribute(task, "duration")?,
+        });
+    }
+    Ok(())
+}
+
+fn read_holidays(
+    calendars: roxmltree::Node,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Date>, GanError> {
+    let mut holidays = vec![];
+    for date in element_children(calendars, "date") {
+        let year = date.attribute("year").unwrap_or("");
+        if year.is_empty() {
+            warnings.push(format!(
+                "Recurring holiday on {}/{} is not supported",
+                date.attribute("month").unwrap_or("?"),
+                date.attribute("date").unwrap_or("?")
+            ));
+            continue;
+        }
+        let year: i32 = parse_attribute(date, "year")?;
+        let month: u8 = parse_attribute(date, "month")?;
+        let day: u8 = parse_attribute(date, "date")?;
+        let holiday = Month::try_from(month)
+            .ok()
+            .and_then(|month| Date::from_calendar_date(year, month, day).ok())
+            .ok_or_else(|| GanError::InvalidValue {
+                element: "date".to_string(),
+                attribute: "date",
+                value: format!("{}-{}-{}", year, month, day),
+            })?;
+        holidays.push(holiday);
+    }
+
+    for week in calendars
+        .descendants()
+        .filter(|x| x.has_tag_name("default-week"))
+    {
+        let weekend = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"]
+            .map(|day| week.attribute(day) == Some("1"));
+        if weekend != [false, false, false, false, false, true, true] {
+            warnings.push(
+                "Working week differs from Monday to Friday, sgantt always uses Monday to Friday"
+                    .to_string(),
+            );
+        }
+    }
+    Ok(holidays)
+}
+
+/// Reads a GanttProject file into a project.
+pub fn read_gan(xml: &str) -> Result<GanImport, GanError> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("project") {
+        return Err(GanError::NotAGanFile);
+    }
+
+    let mut warnings = vec![];
+    let mut gan_tasks = vec![];
+    let mut dependencies = vec![];
+    for tasks in element_children(root, "tasks") {
+        collect_tasks(tasks, &mut gan_tasks, &mut dependencies, &mut warnings)?;
+    }
+
+    let mut resources = vec![];
+    for node in element_children(root, "resources").flat_map(|x| element_children(x, "resource")) {
+        resources.push(Resource {
+            id: parse_attribute(node, "id")?,
+            label: node.attribute("name").unwrap_or("").to_string(),
+            output: GENERIC_RESOURCE_OUTPUT * 5.0,
+            team: None,
+            roles: vec![],
+        });
+    }
+
+    let mut allocations = vec![];
+    for node in
+        element_children(root, "allocations").flat_map(|x| element_children(x, "allocation"))
+    {
+        let load: f32 = parse_attribute(node, "load")?;
+        allocations.push(Allocation {
+            taskid: parse_attribute(node, "task-id")?,
+            resourceid: parse_attribute(node, "resource-id")?,
+            load: load / 100.0,
+        });
+    }
+    allocations.retain(|alloc: &Allocation| {
+        let known = gan_tasks.iter().any(|x| x.id == alloc.taskid);
+        if !known {
+            warnings.push(format!(
+                "Allocation of resource {} to summary task {} was dropped",
+                alloc.resourceid, alloc.taskid
+            ));
+        }
+        known
+    });
+
+    if element_children(root, "vacations")
+        .flat_map(|x| element_children(x, "vacation"))
+        .next()
+        .is_some()
+    {
+        warnings.push("Resource vacations are not supported".to_string());
+    }
+
+    let mut holidays = vec![];
+    for calendars in element_children(root, "calendars") {
+        holidays.append(&mut read_holidays(calendars, &mut warnings)?);
+    }
+
+    // The effort is the duration in days worked by everyone allocated,
+    // tasks without allocations are assumed to be done by one person.
+    let tasks = gan_tasks
+        .into_iter()
+        .map(|gan_task| {
+            let allocated: f32 = allocations
+                .iter()
+                .filter(|x| x.taskid == gan_task.id)
+                .map(|x| x.load)
+                .sum();
+            let planned_resources = if allocated > 0.0 { allocated } else { 1.0 };
+            Task {
+                id: gan_task.id,
+                duration: (gan_task.days * GENERIC_RESOURCE_OUTPUT * planned_resources).round()
+                    as u32,
+                label: gan_task.label,
+                earliest_start_date: gan_task.start,
+                planned_resources,
+                allocated_resources: 0.0,
+                predecessors: vec![],
+                tags: vec![],
+                color: None,
+                role_demand: vec![],
+            }
+        })
+        .collect::<Vec<Task>>();
+
+    let mut project = Project {
+        tasks,
+        resources,
+        allocations,
+        holidays,
+    };
+
+    for (pred_id, succ_id) in dependencies {
+        let pred_known = project.tasks.iter().any(|x| x.id == pred_id);
+        match project.tasks.iter_mut().find(|x| x.id == succ_id) {
+            Some(successor) if pred_known => successor.predecessors.push(pred_id),
+            _ => warnings.push(format!(
+                "Dependency {} -> {} involves a summary task and was dropped",
+                pred_id, succ_id
+            )),
+        }
+    }
+
+    project.validate().map_err(GanError::Invalid)?;
+    Ok(GanImport { project, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::ProjectError;
+
+    use super::{read_gan, GanError};
+
+    const SAMPLE: &str = include_str!("../testinput.gan");
+
+    #[test]
+    pub fn can_read_tasks_and_dependencies() {
+        let import = read_gan(SAMPLE).unwrap();
+        let p = import.project;
+
+        assert_eq!(p.tasks.len(), 4);
+        let design = p.tasks.iter().find(|x| x.id == 1).unwrap();
+        assert_eq!(design.label, "Design");
+        assert_eq!(design.earliest_start_date, date!(2023 - 06 - 05));
+        // 5 days with 1.5 people
+        assert_eq!(design.planned_resources, 1.5);
+        assert_eq!(design.duration, 60);
+
+        let build = p.tasks.iter().find(|x| x.id == 2).unwrap();
+        assert_eq!(build.predecessors, vec![1]);
+        assert_eq!(build.duration, 80);
+    }
+
+    #[test]
+    pub fn can_read_resources_allocations_and_holidays() {
+        let p = read_gan(SAMPLE).unwrap().project;
+
+        assert_eq!(p.resources.len(), 2);
+        assert_eq!(p.resources[1].label, "Bert");
+        assert_eq!(p.allocations.len(), 3);
+        assert_eq!(p.allocations[1].load, 0.5);
+        assert_eq!(p.holidays, vec![date!(2023 - 12 - 25)]);
+    }
+
+    #[test]
+    pub fn reports_unsupported_constructs() {
+        let warnings = read_gan(SAMPLE).unwrap().warnings;
+
+        assert!(warnings.iter().any(|x| x.contains("Summary task 0")));
+        assert!(warnings.iter().any(|x| x.contains("not finish-to-start")));
+        assert!(warnings.iter().any(|x| x.contains("Milestone 4")));
+        assert!(warnings.iter().any(|x| x.contains("Recurring holiday")));
+        assert!(warnings.iter().any(|x| x.contains("vacations")));
+    }
+
+    #[test]
+    pub fn rejects_other_files() {
+        assert!(read_gan("<Project></Project>").is_err());
+        assert!(read_gan("<project><tasks><task id=\"0\"/></tasks></project>").is_err());
+        // Build also before Design
+        let gan = SAMPLE.replacen(
+            "<depend id=\"3\"",
+            "<depend id=\"1\" type=\"2\"/>\n<depend id=\"3\"",
+            1,
+        );
+        assert!(matches!(
+            read_gan(&gan),
+            Err(GanError::Invalid(ProjectError::PredecessorCycle(1)))
+        ));
+    }
+}
@@ -0,0 +1,485 @@
+//! A single-file HTML report with an interactive Gantt chart, the resource
+//! load chart and a sortable task table. Styles, script and data are all
+//! inlined so the report works offline.
+
+use serde::Serialize;
+use time::{Date, Duration};
+
+use crate::pom::{Project, Task, TaskDates};
+use crate::renderer::allocation_label;
+use crate::theme::Theme;
+
+#[derive(Serialize)]
+struct ReportTask {
+    id: u32,
+    label: String,
+    start: String,
+    end: String,
+    /// Days since the first day of the report.
+    start_day: i64,
+    end_day: i64,
+    work_days: f32,
+    planned_resources: f32,
+    allocated_resources: f32,
+    allocations: String,
+    milestone: bool,
+    predecessors: Vec<u32>,
+    group: usize,
+}
+
+/// A run of days with the same load of one resource.
+#[derive(Serialize)]
+struct LoadSegment {
+    start_day: i64,
+    days: i64,
+    /// Relative to the resource's capacity, null for work on a day without.
+    load: f32,
+    color: String,
+}
+
+#[derive(Serialize)]
+struct ResourceLoad {
+    label: String,
+    segments: Vec<LoadSegment>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    first_day: String,
+    days: i64,
+    reference_day: i64,
+    groups: Vec<String>,
+    tasks: Vec<ReportTask>,
+    load: Vec<ResourceLoad>,
+}
+
+const UNASSIGNED_GROUP: &str = "Unassigned";
+
+/// Tasks are grouped by the first resource allocated to them.
+fn group_names(p: &Project) -> Vec<String> {
+    let mut groups: Vec<String> = p.resources.iter().map(|x| x.label.clone()).collect();
+    groups.push(UNASSIGNED_GROUP.to_string());
+    groups
+}
+
+fn task_group(p: &Project, task: &Task) -> usize {
+    p.allocations
+        .iter()
+        .find(|x| x.taskid == task.id)
+        .and_then(|alloc| p.resources.iter().position(|x| x.id == alloc.resourceid))
+        .unwrap_or(p.resources.len())
+}
+
+fn resource_load(
+    p: &Project,
+    dates: &TaskDates,
+    resource_id: u32,
+    first_day: Date,
+    days: i64,
+) -> Vec<LoadSegment> {
+    let theme = Theme::default();
+    let mut segments: Vec<LoadSegment> = vec![];
+    for day in 0..days {
+        let load = p.calculate_relative_load(dates, resource_id, first_day + Duration::days(day));
+        match segments.last_mut() {
+            Some(segment) if segment.load == load => segment.days += 1,
+            _ => segments.push(LoadSegment {
+                start_day: day,
+                days: 1,
+                load,
+                color: theme.load_color(load).to_string(),
+            }),
+        }
+    }
+    segments
+}
+
+fn build_report(p: &Project, reference_date: Date) -> Report {
+    let dates = p.task_dates();
+    let first_day = dates
+        .values()
+        .map(|x| x.0)
+        .min()
+        .unwrap_or(reference_date)
+        .min(reference_date);
+    let last_day = dates
+        .values()
+        .map(|x| x.1)
+        .max()
+        .unwrap_or(reference_date)
+        .max(reference_date);
+    let days = (last_day - first_day).whole_days() + 1;
+    let day_of = |date: Date| (date - first_day).whole_days();
+
+    let tasks = p
+        .tasks
+        .iter()
+        .map(|task| {
+            let (start, end) = dates[&task.id];
+            ReportTask {
+                id: task.id,
+                label: task.label.clone(),
+                start: start.to_string(),
+                end: end.to_string(),
+                start_day: day_of(start),
+                end_day: day_of(end),
+                work_days: task.get_work_days(),
+                planned_resources: task.planned_resources,
+                allocated_resources: task.allocated_resources,
+                allocations: allocation_label(p, task),
+                milestone: task.is_milestone(),
+                predecessors: task.predecessors.clone(),
+                group: task_group(p, task),
+            }
+        })
+        .collect();
+
+    let load = p
+        .resources
+        .iter()
+        .map(|res| ResourceLoad {
+            label: res.label.clone(),
+            segments: resource_load(p, &dates, res.id, first_day, days),
+        })
+        .collect();
+
+    Report {
+        first_day: first_day.to_string(),
+        days,
+        reference_day: day_of(reference_date),
+        groups: group_names(p),
+        tasks,
+        load,
+    }
+}
+
+/// Makes JSON safe to embed in a script element.
+fn escape_script(json: &str) -> String {
+    json.replace("</", "<\\/").replace("<!--", "<\\!--")
+}
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; font-size: 13px; margin: 1em; color: #222; }
+h1 { font-size: 18px; }
+h2 { font-size: 15px; margin-top: 1.5em; }
+.toolbar button { min-width: 2.5em; }
+.chart { display: flex; border: 1px solid #999; }
+.names { flex: none; width: 240px; border-right: 1px solid #999; }
+.names div { height: 18px; line-height: 18px; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; padding-left: 4px; }
+.names .group { font-weight: bold; cursor: pointer; background: #eee; }
+.names .group::before { content: "\25BE  "; }
+.names .group.collapsed::before { content: "\25B8  "; }
+.timeline { overflow-x: auto; overflow-y: hidden; cursor: grab; flex: auto; }
+.timeline.dragging { cursor: grabbing; }
+.timeline svg { display: block; }
+#tooltip { position: fixed; display: none; background: #ffffe0; border: 1px solid #999; padding: 4px 6px; pointer-events: none; white-space: pre; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 2px 6px; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th.ascending::after { content: " \25B4"; }
+th.descending::after { content: " \25BE"; }
+td.number { text-align: right; }
+"#;
+
+const SCRIPT: &str = r##"
+const report = JSON.parse(document.getElementById("report-data").textContent);
+const ROW = 18, HEADER = 18, DAY_MS = 86400000;
+const firstDay = new Date(report.first_day + "T00:00:00Z");
+const collapsed = new Set();
+let dayWidth = 4;
+
+const tooltip = document.getElementById("tooltip");
+function showTooltip(event, text) {
+  tooltip.textContent = text;
+  tooltip.style.display = "block";
+  tooltip.style.left = (event.clientX + 12) + "px";
+  tooltip.style.top = (event.clientY + 12) + "px";
+}
+function hideTooltip() { tooltip.style.display = "none"; }
+
+function escapeHtml(text) {
+  return String(text).replace(/[&<>"]/g, c => ({ "&": "&amp;", "<": "&lt;", ">": "&gt;", '"': "&quot;" })[c]);
+}
+
+function dayDate(day) { return new Date(firstDay.getTime() + day * DAY_MS); }
+
+// Month ticks, labelled with the quarter's start date at quarter starts.
+function axis(height) {
+  let svg = "";
+  for (let day = 0; day <= report.days; day++) {
+    const date = dayDate(day);
+    if (date.getUTCDate() !== 1) continue;
+    const x = day * dayWidth;
+    const quarter = date.getUTCMonth() % 3 === 0;
+    svg += `<line x1="${x}" y1="0" x2="${x}" y2="${height}" stroke="${quarter ? "#666" : "#ccc"}"/>`;
+    if (quarter || dayWidth >= 3) {
+      svg += `<text x="${x + 2}" y="12" font-size="10">${date.toISOString().slice(0, quarter ? 10 : 7)}</text>`;
+    }
+  }
+  const today = report.reference_day * dayWidth;
+  svg += `<line x1="${today}" y1="0" x2="${today}" y2="${height}" stroke="#d00" stroke-dasharray="4 2"/>`;
+  return svg;
+}
+
+function visibleRows() {
+  const rows = [];
+  report.groups.forEach((name, group) => {
+    const tasks = report.tasks.filter(t => t.group === group);
+    if (tasks.length === 0) return;
+    rows.push({ group, name });
+    if (!collapsed.has(group)) tasks.forEach(task => rows.push({ group, task }));
+  });
+  return rows;
+}
+
+function renderGantt() {
+  const rows = visibleRows();
+  const height = HEADER + rows.length * ROW;
+  const width = report.days * dayWidth;
+  const names = document.getElementById("gantt-names");
+  names.innerHTML = `<div>Task</div>` + rows.map(row => row.task
+    ? `<div data-task="${row.task.id}">${escapeHtml(row.task.label)}</div>`
+    : `<div class="group${collapsed.has(row.group) ? " collapsed" : ""}" data-group="${row.group}">${escapeHtml(row.name)}</div>`
+  ).join("");
+
+  const position = new Map();
+  let svg = axis(height);
+  rows.forEach((row, index) => {
+    const y = HEADER + index * ROW;
+    if (!row.task) {
+      svg += `<rect x="0" y="${y}" width="${width}" height="${ROW}" fill="#eee" opacity="0.6"/>`;
+      return;
+    }
+    const t = row.task;
+    const x1 = t.start_day * dayWidth, x2 = t.end_day * dayWidth;
+    position.set(t.id, { x1, x2, y: y + ROW / 2 });
+    if (t.milestone) {
+      const c = y + ROW / 2;
+      svg += `<path data-task="${t.id}" d="M${x1} ${c - 6}L${x1 + 6} ${c}L${x1} ${c + 6}L${x1 - 6} ${c}Z" fill="#444"/>`;
+    } else {
+      svg += `<rect data-task="${t.id}" x="${x1}" y="${y + 4}" width="${Math.max(x2 - x1, 1)}" height="${ROW - 8}" fill="#A0A0CC" stroke="#7979CC"/>`;
+    }
+  });
+  report.tasks.forEach(t => t.predecessors.forEach(id => {
+    const from = position.get(id), to = position.get(t.id);
+    if (!from || !to) return;
+    svg += `<path d="M${from.x2} ${from.y}H${from.x2 + 4}V${to.y}H${to.x1}" fill="none" stroke="blue"/>`;
+  }));
+  const chart = document.getElementById("gantt-svg");
+  chart.setAttribute("width", width);
+  chart.setAttribute("height", height);
+  chart.innerHTML = svg;
+}
+
+function renderLoad() {
+  const height = HEADER + report.load.length * ROW;
+  document.getElementById("load-names").innerHTML = `<div>Resource</div>` +
+    report.load.map(r => `<div>${escapeHtml(r.label)}</div>`).join("");
+  let svg = axis(height);
+  report.load.forEach((resource, index) => {
+    const y = HEADER + index * ROW;
+    resource.segments.forEach((s, segment) => {
+      svg += `<rect data-resource="${index}" data-segment="${segment}" x="${s.start_day * dayWidth}" y="${y + 2}" width="${s.days * dayWidth}" height="${ROW - 4}" fill="${s.color}"/>`;
+    });
+  });
+  const chart = document.getElementById("load-svg");
+  chart.setAttribute("width", report.days * dayWidth);
+  chart.setAttribute("height", height);
+  chart.innerHTML = svg;
+}
+
+function taskTooltip(t) {
+  return `${t.label}\n${t.start} – ${t.end}\n${t.allocations}`;
+}
+
+function render() { renderGantt(); renderLoad(); }
+
+// Zoom keeps the day under the given point of the timeline in place.
+const timelines = [...document.querySelectorAll(".timeline")];
+function zoom(factor, anchor) {
+  const timeline = timelines[0];
+  const offset = anchor === undefined ? timeline.clientWidth / 2 : anchor;
+  const day = (timeline.scrollLeft + offset) / dayWidth;
+  dayWidth = Math.min(64, Math.max(0.25, dayWidth * factor));
+  render();
+  timelines.forEach(t => t.scrollLeft = day * dayWidth - offset);
+}
+document.getElementById("zoom-in").onclick = () => zoom(2);
+document.getElementById("zoom-out").onclick = () => zoom(0.5);
+document.getElementById("zoom-today").onclick = () =>
+  timelines.forEach(t => t.scrollLeft = report.reference_day * dayWidth - t.clientWidth / 2);
+
+timelines.forEach(timeline => {
+  timeline.addEventListener("wheel", event => {
+    if (!event.ctrlKey) return;
+    event.preventDefault();
+    zoom(event.deltaY < 0 ? 1.25 : 0.8, event.clientX - timeline.getBoundingClientRect().left);
+  }, { passive: false });
+  timeline.addEventListener("scroll", () =>
+    timelines.forEach(t => { if (t !== timeline) t.scrollLeft = timeline.scrollLeft; }));
+  let drag = null;
+  timeline.addEventListener("mousedown", event => {
+    drag = { x: event.clientX, scroll: timeline.scrollLeft };
+    timeline.classList.add("dragging");
+  });
+  window.addEventListener("mousemove", event => {
+    if (drag) timeline.scrollLeft = drag.scroll - (event.clientX - drag.x);
+  });
+  window.addEventListener("mouseup", () => { drag = null; timeline.classList.remove("dragging"); });
+});
+
+document.addEventListener("mousemove", event => {
+  const target = event.target.closest("[data-task], [data-segment]");
+  if (!target) return hideTooltip();
+  if (target.dataset.task !== undefined) {
+    const t = report.tasks.find(x => x.id === Number(target.dataset.task));
+    showTooltip(event, taskTooltip(t));
+  } else {
+    const resource = report.load[target.dataset.resource];
+    const s = resource.segments[target.dataset.segment];
+    const from = dayDate(s.start_day).toISOString().slice(0, 10);
+    const to = dayDate(s.start_day + s.days - 1).toISOString().slice(0, 10);
+    showTooltip(event, `${resource.label}\n${from} – ${to}\nLoad: ${s.load === null ? "no capacity" : Math.round(s.load * 100) + "%"}`);
+  }
+});
+
+document.getElementById("gantt-names").addEventListener("click", event => {
+  const group = event.target.dataset.group;
+  if (group === undefined) return;
+  const index = Number(group);
+  collapsed.has(index) ? collapsed.delete(index) : collapsed.add(index);
+  renderGantt();
+});
+
+const columns = [
+  ["id", "ID", true], ["label", "Task", false], ["start", "Start", false], ["end", "End", false],
+  ["work_days", "Work days", true], ["planned_resources", "Planned FTE", true],
+  ["allocated_resources", "Allocated FTE", true], ["allocations", "Resources", false],
+];
+let sortColumn = 0, sortDirection = 1;
+function renderTable() {
+  const [key, , numeric] = columns[sortColumn];
+  const tasks = [...report.tasks].sort((a, b) =>
+    sortDirection * (numeric ? a[key] - b[key] : String(a[key]).localeCompare(String(b[key]))));
+  const head = columns.map(([, title], index) => {
+    const order = index === sortColumn ? (sortDirection > 0 ? "ascending" : "descending") : "";
+    return `<th class="${order}" data-column="${index}">${title}</th>`;
+  }).join("");
+  const body = tasks.map(t => "<tr>" + columns.map(([key, , numeric]) => {
+    const value = key === "work_days" ? Math.round(t[key] * 100) / 100 : t[key];
+    return `<td${numeric ? ' class="number"' : ""}>${escapeHtml(value)}</td>`;
+  }).join("") + "</tr>").join("");
+  document.getElementById("task-table").innerHTML = `<thead><tr>${head}</tr></thead><tbody>${body}</tbody>`;
+}
+document.getElementById("task-table").addEventListener("click", event => {
+  const column = event.target.dataset.column;
+  if (column === undefined) return;
+  sortDirection = Number(column) === sortColumn ? -sortDirection : 1;
+  sortColumn = Number(column);
+  renderTable();
+});
+
+render();
+renderTable();
+document.getElementById("zoom-today").onclick();
+"##;
+
+/// Renders the report for the given reference date, which is marked in both
+/// charts.
+pub fn export_html(p: &Project, reference_date: Date) -> String {
+    let data = serde_json::to_string(&build_report(p, reference_date)).unwrap();
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>sgantt report {reference_date}</title>
+<style>{STYLE}</style>
+</head>
+<body>
+<h1>sgantt report {reference_date}</h1>
+<div class="toolbar">
+<button id="zoom-out" title="Zoom out (Ctrl + wheel)">&minus;</button>
+<button id="zoom-in" title="Zoom in (Ctrl + wheel)">+</button>
+<button id="zoom-today">Reference date</button>
+</div>
+<h2>Gantt chart</h2>
+<div class="chart"><div class="names" id="gantt-names"></div><div class="timeline"><svg id="gantt-svg" xmlns="http://www.w3.org/2000/svg"></svg></div></div>
+<h2>Resource load</h2>
+<div class="chart"><div class="names" id="load-names"></div><div class="timeline"><svg id="load-svg" xmlns="http://www.w3.org/2000/svg"></svg></div></div>
+<h2>Tasks</h2>
+<table id="task-table"></table>
+<div id="tooltip"></div>
+<script type="application/json" id="report-data">{data}</script>
+<script>{SCRIPT}</script>
+</body>
+</html>
+"#,
+        reference_date = reference_date,
+        data = escape_script(&data),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::fixtures::{allocation, project, resource, task};
+    use crate::pom::Project;
+
+    use super::{build_report, export_html};
+
+    fn make_project() -> Project {
+        let monday = date!(2023 - 06 - 05);
+        let mut p = project(
+            vec![
+                task(0, "Design </script>", 40, monday, vec![]),
+                task(1, "Build", 80, monday, vec![0]),
+                task(2, "Release", 0, monday, vec![1]),
+            ],
+            vec![resource(0, "Hans")],
+            vec![allocation(0, 0, 0.5)],
+        );
+        p.calculate_resource_allocations();
+        p
+    }
+
+    #[test]
+    pub fn report_is_self_contained() {
+        let html = export_html(&make_project(), date!(2023 - 06 - 08));
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("<script src"));
+        assert!(!html.contains("<link"));
+        // The label cannot end the data script early.
+        assert_eq!(html.matches("</script>").count(), 2);
+        assert!(html.contains("Design <\\/script>"));
+        assert!(html.contains("\"allocations\":\"Hans:50%, FTE:0.5/1\""));
+    }
+
+    #[test]
+    pub fn groups_tasks_by_first_resource() {
+        let report = build_report(&make_project(), date!(2023 - 06 - 08));
+
+        assert_eq!(report.groups, vec!["Hans", "Unassigned"]);
+        let groups: Vec<usize> = report.tasks.iter().map(|x| x.group).collect();
+        assert_eq!(groups, vec![0, 1, 1]);
+    }
+
+    #[test]
+    pub fn merges_days_with_equal_load() {
+        let report = build_report(&make_project(), date!(2023 - 06 - 08));
+
+        assert_eq!(report.first_day, "2023-06-05");
+        assert_eq!(report.reference_day, 3);
+        let segments = &report.load[0].segments;
+        // Work on Design starts the day after its start.
+        assert_eq!(segments[0].start_day, 0);
+        assert_eq!(segments[0].load, 0.0);
+        assert_eq!(segments[1].start_day, 1);
+        assert_eq!(segments[1].load, 0.5);
+        assert_eq!(segments[2].start_day, 1 + segments[1].days);
+        assert_eq!(segments[2].load, 0.0);
+        assert_eq!(segments.iter().map(|x| x.days).sum::<i64>(), report.days);
+    }
+}
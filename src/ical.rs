@@ -0,0 +1,224 @@
+//! iCalendar (.ics) export of the computed schedule.
+
+use time::{Date, Duration, OffsetDateTime};
+
+use crate::pom::{load_percent, Project, Task};
+
+pub struct IcalOptions {
+    /// Also add an event spanning each task, not only the milestones.
+    pub include_tasks: bool,
+    /// Only add tasks this resource is allocated to, which implies
+    /// `include_tasks`. Milestones are always added since they concern
+    /// everyone on the project.
+    pub resource: Option<u32>,
+    /// Written as DTSTAMP, i.e. when the calendar was generated.
+    pub timestamp: OffsetDateTime,
+}
+
+/// Escapes text values as required by RFC 5545.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds content lines longer than 75 octets, continuation lines start with a space.
+fn fold_line(line: &str) -> String {
+    let mut out = String::new();
+    let mut line_length = 0;
+    for c in line.chars() {
+        if line_length + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            line_length = 1;
+        }
+        out.push(c);
+        line_length += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+fn format_date(date: Date) -> String {
+    format!(
+        "{:04}{:02}{:02}",
+        date.year(),
+        date.month() as u8,
+        date.day()
+    )
+}
+
+fn format_timestamp(timestamp: OffsetDateTime) -> String {
+    format!(
+        "{}T{:02}{:02}{:02}Z",
+        format_date(timestamp.date()),
+        timestamp.hour(),
+        timestamp.minute(),
+        timestamp.second()
+    )
+}
+
+fn allocation_description(p: &Project, task: &Task) -> String {
+    p.allocations
+        .iter()
+        .filter(|x| x.taskid == task.id)
+        .map(|alloc| {
+            format!(
+                "{} {}",
+                p.get_resource_by_id(alloc.resourceid).label,
+                load_percent(alloc.load)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn add_event(out: &mut String, p: &Project, task: &Task, options: &IcalOptions) {
+    let start = task.get_actual_start_date(p);
+    // All day events end on the day after, DTEND is exclusive.
+    let end = task.get_actual_end_date(p) + Duration::days(1);
+    let category = if task.is_milestone() {
+        "Milestone"
+    } else {
+        "Task"
+    };
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:sgantt-task-{}", task.id),
+        format!("DTSTAMP:{}", format_timestamp(options.timestamp)),
+        format!("DTSTART;VALUE=DATE:{}", format_date(start)),
+        format!("DTEND;VALUE=DATE:{}", format_date(end)),
+        format!("SUMMARY:{}", escape_text(&task.label)),
+        format!("CATEGORIES:{}", category),
+    ];
+    let description = allocation_description(p, task);
+    if !description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape_text(&description)));
+    }
+    lines.push("TRANSP:TRANSPARENT".to_string());
+    lines.push("END:VEVENT".to_string());
+
+    for line in lines {
+        out.push_str(&fold_line(&line));
+    }
+}
+
+fn is_allocated(p: &Project, task: &Task, resource_id: u32) -> bool {
+    p.allocations
+        .iter()
+        .any(|x| x.taskid == task.id && x.resourceid == resource_id)
+}
+
+/// Exports the milestones and, if requested, the tasks as all day events.
+pub fn export_ical(p: &Project, options: &IcalOptions) -> String {
+    let mut out = String::new();
+    out.push_str(&fold_line("BEGIN:VCALENDAR"));
+    out.push_str(&fold_line("VERSION:2.0"));
+    out.push_str(&fold_line("PRODID:-//sgantt//sgantt//EN"));
+    out.push_str(&fold_line("CALSCALE:GREGORIAN"));
+
+    for task in p.tasks.iter() {
+        let wanted = if task.is_milestone() {
+            true
+        } else {
+            (options.include_tasks || options.resource.is_some())
+                && options
+                    .resource
+                    .is_none_or(|resource_id| is_allocated(p, task, resource_id))
+        };
+        if wanted {
+            add_event(&mut out, p, task, options);
+        }
+    }
+
+    out.push_str(&fold_line("END:VCALENDAR"));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use crate::pom::fixtures::{allocation, project, resource, task};
+    use crate::pom::Project;
+
+    use super::{export_ical, fold_line, IcalOptions};
+
+    fn make_project() -> Project {
+        let start = date!(2023 - 06 - 08);
+        project(
+            vec![
+                task(0, "Design, first draft", 40, start, vec![]),
+                task(1, "Build", 40, start, vec![]),
+                task(2, "Release", 0, start, vec![]),
+            ],
+            vec![resource(0, "Hans"), resource(1, "Ernst")],
+            vec![allocation(0, 0, 1.0), allocation(1, 1, 0.5)],
+        )
+    }
+
+    fn options(include_tasks: bool, resource: Option<u32>) -> IcalOptions {
+        IcalOptions {
+            include_tasks,
+            resource,
+            timestamp: datetime!(2023-06-01 12:30:00 UTC),
+        }
+    }
+
+    #[test]
+    pub fn exports_only_milestones_by_default() {
+        let out = export_ical(&make_project(), &options(false, None));
+
+        assert!(out.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(out.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(out.matches("BEGIN:VEVENT").count(), 1);
+        assert!(out.contains(
+            "UID:sgantt-task-2\r\n\
+             DTSTAMP:20230601T123000Z\r\n\
+             DTSTART;VALUE=DATE:20230608\r\n\
+             DTEND;VALUE=DATE:20230609\r\n\
+             SUMMARY:Release\r\n\
+             CATEGORIES:Milestone\r\n"
+        ));
+    }
+
+    #[test]
+    pub fn exports_task_spans() {
+        let out = export_ical(&make_project(), &options(true, None));
+
+        assert_eq!(out.matches("BEGIN:VEVENT").count(), 3);
+        // 5 working days from Thursday, ending the Thursday after
+        assert!(out.contains(
+            "DTSTART;VALUE=DATE:20230608\r\n\
+             DTEND;VALUE=DATE:20230616\r\n\
+             SUMMARY:Design\\, first draft\r\n\
+             CATEGORIES:Task\r\n\
+             DESCRIPTION:Hans 100%\r\n"
+        ));
+    }
+
+    #[test]
+    pub fn filters_tasks_by_resource() {
+        let mut p = make_project();
+        p.allocations[1].load = 1.0 / 3.0;
+        // Filtering by resource implies the tasks.
+        let out = export_ical(&p, &options(false, Some(1)));
+
+        assert_eq!(out.matches("BEGIN:VEVENT").count(), 2);
+        assert!(out.contains("SUMMARY:Build\r\n"));
+        assert!(out.contains("DESCRIPTION:Ernst 33%\r\n"));
+        assert!(out.contains("SUMMARY:Release\r\n"));
+        assert!(!out.contains("SUMMARY:Design"));
+    }
+
+    #[test]
+    pub fn folds_long_lines() {
+        let line = format!("SUMMARY:{}", "x".repeat(100));
+        let folded = fold_line(&line);
+
+        assert_eq!(folded.len(), line.len() + 3 + 2);
+        assert!(folded.lines().all(|x| x.len() <= 75));
+        assert!(folded.contains("\r\n x"));
+    }
+}
@@ -1,13 +1,501 @@
-use time::macros::date;
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::Path,
+    process,
+};
 
+use time::macros::{date, format_description};
+
+mod csv_import;
+mod font;
+mod ganttproject;
+mod html;
+mod ical;
+mod mspdi;
+mod overload;
+mod plantuml;
 mod pom;
+mod raster;
 mod renderer;
+mod staffing;
+mod taskjuggler;
+mod terminal;
+mod theme;
+mod typeset;
 
-fn main() {
-    let mut the_project = pom::load_project("testinput.json");
+const USAGE: &str = "Usage:
+    sgantt                                   render testinput.json to image.svg and load_chart.svg
+    sgantt render <project> <out>            render charts as SVG, PNG or PDF, chosen by the extension of out
+        --chart <gantt|lanes|load|teams|both>
+                                             which charts to render, defaults to gantt. Lanes groups the
+                                             tasks by resource, teams shows the demand on each team of
+                                             resources against its capacity. Both charts become two pages
+                                             of a PDF or two files, the load chart's ending in _load
+        --date <YYYY-MM-DD>                  the charts' reference date, defaults to today
+        --scale <factor> | --dpi <dpi>       PNG resolution, defaults to one pixel per SVG unit (96 DPI)
+        --day-width <units>                  SVG units per day, defaults to 1
+        --from <YYYY-MM-DD>                  the first day shown, defaults to the reference date
+        --to <YYYY-MM-DD>                    the last day shown, defaults to the end of the horizon or the
+                                             last task's end
+        --horizon <days>                     the days shown if there is no --to, defaults to 365
+        --header <units>                     header tiers from top to bottom, e.g. year,quarter,month;
+                                             units: year, quarter, month, week, day. Chosen from the
+                                             day width by default
+        --label-width <units>                width of the label column, defaults to the longest label
+                                             up to 300. Longer labels are shortened with an ellipsis
+                                             and show the full text as tooltip
+        --load-period <unit>                 average the load and teams charts over days (default), weeks,
+                                             months, quarters or years
+        --load-view <bands|histogram>        colour the load by band (default), or stack it by task.
+                                             Load is relative to each resource's capacity per day;
+                                             the theme's load_bands set the bands' upper bounds
+                                             (up_to, 1.0 is full capacity) and colours
+        --theme <name|file>                  colours, font size and row heights: light (default), dark,
+                                             print, colorblind, or a JSON, YAML or TOML file with the
+                                             keys to change and optionally the preset to start from
+                                             as base
+        --tag-color <tag>=<color>            colour the bars of tasks with the tag, may be repeated.
+                                             A task's own color comes first, then the first rule
+                                             matching one of its tags; rules also come from the
+                                             theme's tag_colors. Used rules are listed in a legend
+    sgantt text <project>                    draw the charts in the terminal
+        --chart <gantt|load|both>            which charts to draw, defaults to gantt
+        --date <YYYY-MM-DD>                  the charts' reference date, defaults to today
+        --width <columns>                    defaults to $COLUMNS or 100
+        --ascii                              only use ASCII characters
+        --no-color                           no ANSI colours, also when NO_COLOR is set or not on a terminal
+        --theme <name|file>                  the load bands and their colours, as for render
+    sgantt overload <project> [out]          list the days on which resources have more work than capacity
+        --from <YYYY-MM-DD>                  the first day checked, defaults to the project's start
+        --to <YYYY-MM-DD>                    the last day checked, defaults to the project's end
+        --format <text|json>                 defaults to text
+        --check                              exit with status 2 if there are overloads, for CI
+    sgantt staffing <project> [out]          list the role demand of each task that its allocations leave
+                                             unfilled, and the unfilled demand per week
+        --format <text|json>                 defaults to text
+    sgantt export <format> <project> [out]   export a project, formats: plantuml, mspdi, ical, tjp,
+                                             pgfgantt, typst, html
+                                             projects are read as JSON, YAML (.yaml, .yml) or TOML (.toml)
+        ical: --tasks                        add events for all tasks, not only milestones
+              --resource <name or id>        only add the tasks allocated to this resource,
+                                             implies --tasks
+        pgfgantt, typst, html: --date <YYYY-MM-DD>
+                                             the chart's reference date, defaults to today
+    sgantt import <format> <file> [out]      convert a file to a JSON project, formats: mspdi, gan
+    sgantt import-csv <tasks.csv> <resources.csv> <allocations.csv> [out]
+                                             build a JSON project from CSV tables";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1)
+}
+
+fn load_scheduled_project(file_name: &str) -> pom::Project {
+    let mut the_project = pom::read_project(file_name).unwrap_or_else(|e| {
+        eprintln!("Cannot load {}: {}", file_name, e);
+        process::exit(1)
+    });
     the_project.calculate_resource_allocations();
-    let gantt = renderer::render_gantt(&the_project, date!(2023 - 06 - 08));
+    the_project
+}
+
+/// Writes to the given file or, if there is none, to stdout.
+fn write_output(output: Option<&String>, content: &str) {
+    match output {
+        Some(file_name) => fs::write(file_name, content).unwrap(),
+        None => print!("{}", content),
+    }
+}
+
+/// Removes `--name` from the arguments and returns whether it was there.
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let found = args.iter().position(|x| x == name);
+    if let Some(index) = found {
+        args.remove(index);
+    }
+    found.is_some()
+}
+
+/// Removes `--name value` from the arguments and returns the value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let index = args.iter().position(|x| x == name)?;
+    if index + 1 >= args.len() {
+        usage()
+    }
+    args.remove(index);
+    Some(args.remove(index))
+}
+
+fn parse_date(text: &str) -> time::Date {
+    time::Date::parse(text, format_description!("[year]-[month]-[day]")).unwrap_or_else(|_| {
+        eprintln!("Not a date: {}, expected YYYY-MM-DD", text);
+        process::exit(1)
+    })
+}
+
+fn parse_date_option(value: Option<String>) -> time::Date {
+    match value {
+        Some(text) => parse_date(&text),
+        None => time::OffsetDateTime::now_utc().date(),
+    }
+}
+
+fn find_resource(the_project: &pom::Project, name: &str) -> u32 {
+    let resource = the_project
+        .resources
+        .iter()
+        .find(|x| x.label == name || x.id.to_string() == name);
+    match resource {
+        Some(resource) => resource.id,
+        None => {
+            eprintln!("There is no resource {}", name);
+            process::exit(1)
+        }
+    }
+}
+
+fn render_charts() {
+    let the_project = load_scheduled_project("testinput.json");
+    let options = renderer::RenderOptions::default();
+    let gantt = renderer::render_gantt(&the_project, date!(2023 - 06 - 08), &options);
     svg::save("image.svg", &gantt).unwrap();
-    let load_chart = renderer::render_resource_load_chart(the_project, date!(2023 - 06 - 08));
+    let load_chart =
+        renderer::render_resource_load_chart(the_project, date!(2023 - 06 - 08), &options);
     svg::save("load_chart.svg", &load_chart).unwrap();
 }
+
+/// Removes the --theme option from the arguments and loads the theme.
+fn take_theme(args: &mut Vec<String>) -> Option<theme::Theme> {
+    take_option(args, "--theme").map(|name| {
+        theme::load_theme(&name).unwrap_or_else(|e| {
+            eprintln!("Cannot load theme {}: {}", name, e);
+            process::exit(1)
+        })
+    })
+}
+
+/// Removes the options shared by all SVG based charts from the arguments.
+fn take_render_options(args: &mut Vec<String>) -> renderer::RenderOptions {
+    let defaults = renderer::RenderOptions::default();
+    let parse_unit = |name: &str| {
+        renderer::TimeUnit::from_name(name.trim()).unwrap_or_else(|| {
+            eprintln!("Not a time unit: {}", name);
+            process::exit(1)
+        })
+    };
+    let header =
+        take_option(args, "--header").map(|text| text.split(',').map(parse_unit).collect());
+    let load_period = take_option(args, "--load-period").map(|x| parse_unit(&x));
+    let load_view = take_option(args, "--load-view").map(|name| {
+        renderer::LoadView::from_name(&name).unwrap_or_else(|| {
+            eprintln!("Not a load view: {}, expected bands or histogram", name);
+            process::exit(1)
+        })
+    });
+    let mut theme = take_theme(args).unwrap_or(defaults.theme);
+    // Rules given on the command line come before those of the theme.
+    let tag_colors: Vec<theme::TagColor> = std::iter::from_fn(|| take_option(args, "--tag-color"))
+        .map(|rule| match rule.split_once('=') {
+            Some((tag, color)) if !tag.is_empty() && !color.is_empty() => theme::TagColor {
+                tag: tag.to_string(),
+                color: color.to_string(),
+            },
+            _ => {
+                eprintln!("Not a tag colour: {}, expected <tag>=<color>", rule);
+                process::exit(1)
+            }
+        })
+        .collect();
+    theme.tag_colors.splice(0..0, tag_colors);
+    renderer::RenderOptions {
+        day_width: parse_number_option(take_option(args, "--day-width"))
+            .unwrap_or(defaults.day_width),
+        window_start: take_option(args, "--from").map(|x| parse_date(&x)),
+        window_end: take_option(args, "--to").map(|x| parse_date(&x)),
+        horizon_days: parse_number_option(take_option(args, "--horizon"))
+            .map_or(defaults.horizon_days, |x| x as u32),
+        header: header.unwrap_or(defaults.header),
+        label_width: parse_number_option(take_option(args, "--label-width")),
+        load_period: load_period.unwrap_or(defaults.load_period),
+        load_view: load_view.unwrap_or(defaults.load_view),
+        theme,
+    }
+}
+
+fn parse_number_option(value: Option<String>) -> Option<f32> {
+    value.map(|text| match text.parse::<f32>() {
+        Ok(number) if number > 0.0 => number,
+        _ => {
+            eprintln!("Not a positive number: {}", text);
+            process::exit(1)
+        }
+    })
+}
+
+/// Writes the charts to the output file. A PDF gets one page per chart, other
+/// formats get one file per chart with the chart's suffix added to the name.
+fn write_charts(output: &str, charts: Vec<(&str, svg::Document)>, scale: f32) {
+    let path = Path::new(output);
+    let extension = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let fail = |e: String| -> ! {
+        eprintln!("Cannot write {}: {}", output, e);
+        process::exit(1)
+    };
+
+    if extension == "pdf" {
+        let documents: Vec<svg::Document> = charts.into_iter().map(|(_, x)| x).collect();
+        let pdf = raster::render_pdf(&documents).unwrap_or_else(|e| fail(e.to_string()));
+        fs::write(output, pdf).unwrap_or_else(|e| fail(e.to_string()));
+        return;
+    }
+
+    let single_chart = charts.len() == 1;
+    for (suffix, document) in charts {
+        let file_name = if single_chart {
+            output.to_string()
+        } else {
+            let stem = path.file_stem().and_then(|x| x.to_str()).unwrap_or("");
+            path.with_file_name(format!("{}{}.{}", stem, suffix, extension))
+                .to_string_lossy()
+                .to_string()
+        };
+        let result = match extension.as_str() {
+            "svg" => svg::save(&file_name, &document).map_err(|e| e.to_string()),
+            "png" => raster::render_png(&document, scale)
+                .map_err(|e| e.to_string())
+                .and_then(|png| fs::write(&file_name, png).map_err(|e| e.to_string())),
+            _ => usage(),
+        };
+        result.unwrap_or_else(|e| fail(e));
+    }
+}
+
+fn render(args: &[String]) {
+    let mut args = args.to_vec();
+    let reference_date = parse_date_option(take_option(&mut args, "--date"));
+    let chart = take_option(&mut args, "--chart").unwrap_or("gantt".to_string());
+    let scale = parse_number_option(take_option(&mut args, "--scale"));
+    let dpi = parse_number_option(take_option(&mut args, "--dpi"));
+    let options = take_render_options(&mut args);
+    let (Some(project_file), Some(output)) = (args.first(), args.get(1)) else {
+        usage()
+    };
+    let the_project = load_scheduled_project(project_file);
+
+    let mut charts = vec![];
+    if chart == "gantt" || chart == "both" {
+        charts.push((
+            "",
+            renderer::render_gantt(&the_project, reference_date, &options),
+        ));
+    }
+    if chart == "lanes" {
+        charts.push((
+            "",
+            renderer::render_swimlanes(&the_project, reference_date, &options),
+        ));
+    }
+    if chart == "teams" {
+        charts.push((
+            "",
+            renderer::render_team_demand_chart(&the_project, reference_date, &options),
+        ));
+    }
+    if chart == "load" || chart == "both" {
+        charts.push((
+            "_load",
+            renderer::render_resource_load_chart(the_project.clone(), reference_date, &options),
+        ));
+    }
+    if charts.is_empty() {
+        usage()
+    }
+
+    let scale = scale.or(dpi.map(|x| x / raster::SVG_DPI)).unwrap_or(1.0);
+    write_charts(output, charts, scale);
+}
+
+fn text(args: &[String]) {
+    let mut args = args.to_vec();
+    let reference_date = parse_date_option(take_option(&mut args, "--date"));
+    let chart = take_option(&mut args, "--chart").unwrap_or("gantt".to_string());
+    let width = parse_number_option(take_option(&mut args, "--width"))
+        .map(|x| x as usize)
+        .or(env::var("COLUMNS").ok().and_then(|x| x.parse().ok()))
+        .unwrap_or(100);
+    let ascii = take_flag(&mut args, "--ascii");
+    let theme = take_theme(&mut args).unwrap_or_default();
+    let no_color = take_flag(&mut args, "--no-color")
+        || env::var_os("NO_COLOR").is_some()
+        || !io::stdout().is_terminal();
+    let Some(project_file) = args.first() else {
+        usage()
+    };
+    let the_project = load_scheduled_project(project_file);
+    let options = terminal::TerminalOptions {
+        width,
+        color: !no_color,
+        ascii,
+        theme,
+    };
+
+    let content = match chart.as_str() {
+        "gantt" => terminal::render_gantt_text(&the_project, reference_date, &options),
+        "load" => terminal::render_load_text(&the_project, reference_date, &options),
+        "both" => format!(
+            "{}\n{}",
+            terminal::render_gantt_text(&the_project, reference_date, &options),
+            terminal::render_load_text(&the_project, reference_date, &options)
+        ),
+        _ => usage(),
+    };
+    print!("{}", content);
+}
+
+fn overload(args: &[String]) {
+    let mut args = args.to_vec();
+    let from = take_option(&mut args, "--from");
+    let to = take_option(&mut args, "--to");
+    let format = take_option(&mut args, "--format").unwrap_or("text".to_string());
+    let check = take_flag(&mut args, "--check");
+    let Some(project_file) = args.first() else {
+        usage()
+    };
+    let the_project = load_scheduled_project(project_file);
+    let today = time::OffsetDateTime::now_utc().date();
+    let (start, end) = overload::project_span(&the_project).unwrap_or((today, today));
+    let report = overload::find_overloads(
+        &the_project,
+        from.map_or(start, |x| parse_date(&x)),
+        to.map_or(end, |x| parse_date(&x)),
+    );
+    let content = match format.as_str() {
+        "text" => overload::format_overload_report(&report),
+        "json" => serde_json::to_string_pretty(&report).unwrap() + "\n",
+        _ => usage(),
+    };
+    write_output(args.get(1), &content);
+    if check && report.has_overloads() {
+        process::exit(2)
+    }
+}
+
+fn staffing(args: &[String]) {
+    let mut args = args.to_vec();
+    let format = take_option(&mut args, "--format").unwrap_or("text".to_string());
+    let Some(project_file) = args.first() else {
+        usage()
+    };
+    let report = staffing::find_unfilled_roles(&load_scheduled_project(project_file));
+    let content = match format.as_str() {
+        "text" => staffing::format_staffing_report(&report),
+        "json" => serde_json::to_string_pretty(&report).unwrap() + "\n",
+        _ => usage(),
+    };
+    write_output(args.get(1), &content);
+}
+
+fn export(args: &[String]) {
+    let mut args = args.to_vec();
+    let include_tasks = take_flag(&mut args, "--tasks");
+    let resource = take_option(&mut args, "--resource");
+    let reference_date = parse_date_option(take_option(&mut args, "--date"));
+    let (Some(format), Some(project_file)) = (args.first(), args.get(1)) else {
+        usage()
+    };
+    let the_project = load_scheduled_project(project_file);
+    let content = match format.as_str() {
+        "plantuml" => plantuml::export_plantuml(&the_project),
+        "mspdi" => mspdi::write_mspdi(&the_project),
+        "tjp" => taskjuggler::export_taskjuggler(&the_project),
+        "pgfgantt" => typeset::render_pgfgantt(&the_project, reference_date),
+        "typst" => typeset::render_typst(&the_project, reference_date),
+        "html" => html::export_html(&the_project, reference_date),
+        "ical" => ical::export_ical(
+            &the_project,
+            &ical::IcalOptions {
+                include_tasks,
+                resource: resource.map(|x| find_resource(&the_project, &x)),
+                timestamp: time::OffsetDateTime::now_utc(),
+            },
+        ),
+        _ => usage(),
+    };
+    write_output(args.get(2), &content);
+}
+
+fn read_input(file_name: &str) -> String {
+    fs::read_to_string(file_name).unwrap_or_else(|e| {
+        eprintln!("Cannot read {}: {}", file_name, e);
+        process::exit(1)
+    })
+}
+
+fn write_project(output: Option<&String>, the_project: &pom::Project) {
+    let json = serde_json::to_string_pretty(the_project).unwrap();
+    write_output(output, &(json + "\n"));
+}
+
+fn import(args: &[String]) {
+    let (Some(format), Some(input_file)) = (args.first(), args.get(1)) else {
+        usage()
+    };
+    let input = read_input(input_file);
+    let result = match format.as_str() {
+        "mspdi" => mspdi::read_mspdi(&input).map_err(|e| e.to_string()),
+        "gan" => ganttproject::read_gan(&input)
+            .map(|import| {
+                for warning in import.warnings.iter() {
+                    eprintln!("Warning: {}", warning);
+                }
+                import.project
+            })
+            .map_err(|e| e.to_string()),
+        _ => usage(),
+    };
+    let the_project = result.unwrap_or_else(|e| {
+        eprintln!("Cannot import {}: {}", input_file, e);
+        process::exit(1)
+    });
+    write_project(args.get(2), &the_project);
+}
+
+fn import_csv(args: &[String]) {
+    if args.len() < 3 {
+        usage()
+    }
+    let csv_file = |file_name: &String| csv_import::CsvFile {
+        name: file_name.clone(),
+        content: read_input(file_name),
+    };
+    let the_project = csv_import::read_csv_project(
+        &csv_file(&args[0]),
+        &csv_file(&args[1]),
+        &csv_file(&args[2]),
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("Cannot import: {}", e);
+        process::exit(1)
+    });
+    write_project(args.get(3), &the_project);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|x| x.as_str()) {
+        None => render_charts(),
+        Some("render") => render(&args[1..]),
+        Some("text") => text(&args[1..]),
+        Some("overload") => overload(&args[1..]),
+        Some("staffing") => staffing(&args[1..]),
+        Some("export") => export(&args[1..]),
+        Some("import") => import(&args[1..]),
+        Some("import-csv") => import_csv(&args[1..]),
+        Some(_) => usage(),
+    }
+}
@@ -0,0 +1,518 @@
+//! Reading and writing Microsoft Project XML (MSPDI) files.
+//!
+//! MS Project reserves UID 0 for the project summary task and for a
+//! placeholder resource, so sgantt ids are written as UID - 1 and read back
+//! the same way.
+
+use std::fmt;
+
+use time::{macros::format_description, Date};
+
+use crate::pom::{Allocation, Project, ProjectError, Resource, Task, GENERIC_RESOURCE_OUTPUT};
+
+const NAMESPACE: &str = "http://schemas.microsoft.com/project";
+const MINUTES_PER_DAY: f32 = GENERIC_RESOURCE_OUTPUT * 60.0;
+const MINUTES_PER_WEEK: f32 = MINUTES_PER_DAY * 5.0;
+
+/// ConstraintType "Start No Earlier Than"
+const CONSTRAINT_START_NO_EARLIER_THAN: &str = "4";
+/// ConstraintType "Must Start On"
+const CONSTRAINT_MUST_START_ON: &str = "2";
+/// PredecessorLink type "Finish-to-Start"
+const LINK_FINISH_TO_START: &str = "1";
+/// The ResourceUID MS Project uses for assignments without a resource.
+const UNASSIGNED_RESOURCE_UID: &str = "-65535";
+
+#[derive(Debug)]
+pub enum MspdiError {
+    Xml(roxmltree::Error),
+    MissingElement(&'static str),
+    InvalidValue { element: String, value: String },
+    Invalid(ProjectError),
+}
+
+impl fmt::Display for MspdiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MspdiError::Xml(e) => write!(f, "Not a valid XML file: {}", e),
+            MspdiError::MissingElement(name) => write!(f, "Missing element <{}>", name),
+            MspdiError::InvalidValue { element, value } => {
+                write!(f, "Invalid value '{}' in <{}>", value, element)
+            }
+            MspdiError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<roxmltree::Error> for MspdiError {
+    fn from(e: roxmltree::Error) -> Self {
+        MspdiError::Xml(e)
+    }
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn invalid_value(node: roxmltree::Node, value: &str) -> MspdiError {
+    MspdiError::InvalidValue {
+        element: node.tag_name().name().to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// Formats a number of hours as an ISO 8601 duration, e.g. `PT12H30M0S`.
+fn format_duration(hours: f32) -> String {
+    let total_seconds = (hours * 3600.0).round() as u64;
+    format!(
+        "PT{}H{}M{}S",
+        total_seconds / 3600,
+        (total_seconds % 3600) / 60,
+        total_seconds % 60
+    )
+}
+
+/// Parses an ISO 8601 duration as written by MS Project into hours.
+fn parse_duration(text: &str) -> Option<f32> {
+    let rest = text.strip_prefix('P')?;
+    let mut hours = 0.0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' | '.' => number.push(c),
+            unit => {
+                let value: f32 = number.parse().ok()?;
+                number.clear();
+                hours += match (in_time, unit) {
+                    (false, 'D') => value * GENERIC_RESOURCE_OUTPUT,
+                    (true, 'H') => value,
+                    (true, 'M') => value / 60.0,
+                    (true, 'S') => value / 3600.0,
+                    _ => return None,
+                };
+            }
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(hours)
+}
+
+fn format_date_time(date: Date, time_of_day: &str) -> String {
+    format!("{}T{}", date, time_of_day)
+}
+
+fn parse_date_time(text: &str) -> Option<Date> {
+    let date_part = text.get(0..10)?;
+    Date::parse(date_part, format_description!("[year]-[month]-[day]")).ok()
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children()
+        .find(|x| x.is_element() && x.tag_name().name() == name)
+}
+
+fn children<'a, 'input: 'a>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |x| x.is_element() && x.tag_name().name() == name)
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|x| x.text()).map(|x| x.trim())
+}
+
+fn required_child_text<'a>(
+    node: roxmltree::Node<'a, '_>,
+    name: &'static str,
+) -> Result<&'a str, MspdiError> {
+    child_text(node, name).ok_or(MspdiError::MissingElement(name))
+}
+
+/// Reads a UID and turns it into an sgantt id. Returns None for UID 0, which
+/// MS Project reserves for the project summary task and the blank resource.
+fn parse_uid(node: roxmltree::Node, name: &'static str) -> Result<Option<u32>, MspdiError> {
+    let text = required_child_text(node, name)?;
+    let uid: i64 = text
+        .parse()
+        .map_err(|_| invalid_value(child(node, name).unwrap(), text))?;
+    match uid {
+        0 => Ok(None),
+        uid if uid > 0 && uid <= u32::MAX as i64 => Ok(Some(uid as u32 - 1)),
+        _ => Err(invalid_value(child(node, name).unwrap(), text)),
+    }
+}
+
+fn parse_f32_child(node: roxmltree::Node, name: &'static str) -> Result<Option<f32>, MspdiError> {
+    match child_text(node, name) {
+        Some(text) => text
+            .parse()
+            .map(Some)
+            .map_err(|_| invalid_value(child(node, name).unwrap(), text)),
+        None => Ok(None),
+    }
+}
+
+fn parse_duration_child(
+    node: roxmltree::Node,
+    name: &'static str,
+) -> Result<Option<f32>, MspdiError> {
+    match child_text(node, name) {
+        Some(text) => parse_duration(text)
+            .map(Some)
+            .ok_or_else(|| invalid_value(child(node, name).unwrap(), text)),
+        None => Ok(None),
+    }
+}
+
+fn parse_date_child(node: roxmltree::Node, name: &'static str) -> Result<Option<Date>, MspdiError> {
+    match child_text(node, name) {
+        Some(text) => parse_date_time(text)
+            .map(Some)
+            .ok_or_else(|| invalid_value(child(node, name).unwrap(), text)),
+        None => Ok(None),
+    }
+}
+
+fn read_task(node: roxmltree::Node) -> Result<Option<Task>, MspdiError> {
+    let Some(id) = parse_uid(node, "UID")? else {
+        return Ok(None);
+    };
+    if child_text(node, "Summary") == Some("1") {
+        return Ok(None);
+    }
+
+    // MS Project stores the effort as "Work" and the span in working time as
+    // "Duration", the ratio between the two is the number of people on it.
+    let work = parse_duration_child(node, "Work")?.unwrap_or(0.0);
+    let duration = parse_duration_child(node, "Duration")?.unwrap_or(0.0);
+    let (effort, planned_resources) = match (work > 0.0, duration > 0.0) {
+        (true, true) => (work, work / duration),
+        (true, false) => (work, 1.0),
+        (false, _) => (duration, 1.0),
+    };
+
+    let constraint_date = match child_text(node, "ConstraintType") {
+        Some(CONSTRAINT_START_NO_EARLIER_THAN) | Some(CONSTRAINT_MUST_START_ON) => {
+            parse_date_child(node, "ConstraintDate")?
+        }
+        _ => None,
+    };
+    let earliest_start_date = match constraint_date {
+        Some(date) => date,
+        None => parse_date_child(node, "Start")?.ok_or(MspdiError::MissingElement("Start"))?,
+    };
+
+    let mut predecessors = vec![];
+    for link in children(node, "PredecessorLink") {
+        if let Some(pred_id) = parse_uid(link, "PredecessorUID")? {
+            predecessors.push(pred_id);
+        }
+    }
+
+    Ok(Some(Task {
+        id,
+        duration: effort.round() as u32,
+        label: child_text(node, "Name").unwrap_or("").to_string(),
+        earliest_start_date,
+        planned_resources,
+        allocated_resources: 0.0,
+        predecessors,
+        tags: vec![],
+        color: None,
+        role_demand: vec![],
+    }))
+}
+
+fn read_resource(
+    node: roxmltree::Node,
+    hours_per_week: f32,
+) -> Result<Option<Resource>, MspdiError> {
+    let Some(id) = parse_uid(node, "UID")? else {
+        return Ok(None);
+    };
+    let max_units = parse_f32_child(node, "MaxUnits")?.unwrap_or(1.0);
+    Ok(Some(Resource {
+        id,
+        label: child_text(node, "Name").unwrap_or("").to_string(),
+        output: max_units * hours_per_week,
+        team: child_text(node, "Group")
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_string()),
+        roles: vec![],
+    }))
+}
+
+fn read_assignment(node: roxmltree::Node) -> Result<Option<Allocation>, MspdiError> {
+    if child_text(node, "ResourceUID") == Some(UNASSIGNED_RESOURCE_UID) {
+        return Ok(None);
+    }
+    let (Some(taskid), Some(resourceid)) =
+        (parse_uid(node, "TaskUID")?, parse_uid(node, "ResourceUID")?)
+    else {
+        return Ok(None);
+    };
+    Ok(Some(Allocation {
+        taskid,
+        resourceid,
+        load: parse_f32_child(node, "Units")?.unwrap_or(1.0),
+    }))
+}
+
+/// Reads an MSPDI document into a project. Summary tasks and the placeholder
+/// entries MS Project adds for UID 0 are skipped.
+pub fn read_mspdi(xml: &str) -> Result<Project, MspdiError> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "Project" {
+        return Err(MspdiError::MissingElement("Project"));
+    }
+
+    let hours_per_week =
+        parse_f32_child(root, "MinutesPerWeek")?.unwrap_or(MINUTES_PER_WEEK) / 60.0;
+
+    let mut project = Project {
+        tasks: vec![],
+        resources: vec![],
+        allocations: vec![],
+        holidays: vec![],
+    };
+
+    if let Some(tasks) = child(root, "Tasks") {
+        for node in children(tasks, "Task") {
+            if let Some(task) = read_task(node)? {
+                project.tasks.push(task);
+            }
+        }
+    }
+    if let Some(resources) = child(root, "Resources") {
+        for node in children(resources, "Resource") {
+            if let Some(resource) = read_resource(node, hours_per_week)? {
+                project.resources.push(resource);
+            }
+        }
+    }
+    if let Some(assignments) = child(root, "Assignments") {
+        for node in children(assignments, "Assignment") {
+            if let Some(allocation) = read_assignment(node)? {
+                project.allocations.push(allocation);
+            }
+        }
+    }
+
+    project.validate().map_err(MspdiError::Invalid)?;
+    Ok(project)
+}
+
+fn write_task(out: &mut String, p: &Project, task: &Task) {
+    let planned_resources = if task.planned_resources > 0.0 {
+        task.planned_resources
+    } else {
+        1.0
+    };
+    let work = task.duration as f32;
+
+    out.push_str("    <Task>\n");
+    out.push_str(&format!("      <UID>{}</UID>\n", task.id + 1));
+    out.push_str(&format!("      <ID>{}</ID>\n", task.id + 1));
+    out.push_str(&format!("      <Name>{}</Name>\n", escape_xml(&task.label)));
+    out.push_str(&format!(
+        "      <Start>{}</Start>\n",
+        format_date_time(task.get_actual_start_date(p), "08:00:00")
+    ));
+    out.push_str(&format!(
+        "      <Finish>{}</Finish>\n",
+        format_date_time(task.get_actual_end_date(p), "17:00:00")
+    ));
+    out.push_str(&format!(
+        "      <Duration>{}</Duration>\n",
+        format_duration(work / planned_resources)
+    ));
+    out.push_str(&format!("      <Work>{}</Work>\n", format_duration(work)));
+    out.push_str(&format!(
+        "      <ConstraintType>{}</ConstraintType>\n",
+        CONSTRAINT_START_NO_EARLIER_THAN
+    ));
+    out.push_str(&format!(
+        "      <ConstraintDate>{}</ConstraintDate>\n",
+        format_date_time(task.earliest_start_date, "08:00:00")
+    ));
+    for pred_id in task.predecessors.iter() {
+        out.push_str("      <PredecessorLink>\n");
+        out.push_str(&format!(
+            "        <PredecessorUID>{}</PredecessorUID>\n",
+            pred_id + 1
+        ));
+        out.push_str(&format!("        <Type>{}</Type>\n", LINK_FINISH_TO_START));
+        out.push_str("      </PredecessorLink>\n");
+    }
+    out.push_str("    </Task>\n");
+}
+
+/// Writes the project as an MSPDI document that MS Project can open.
+pub fn write_mspdi(p: &Project) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+    out.push_str(&format!("<Project xmlns=\"{}\">\n", NAMESPACE));
+    out.push_str(&format!(
+        "  <MinutesPerDay>{}</MinutesPerDay>\n",
+        MINUTES_PER_DAY
+    ));
+    out.push_str(&format!(
+        "  <MinutesPerWeek>{}</MinutesPerWeek>\n",
+        MINUTES_PER_WEEK
+    ));
+
+    out.push_str("  <Tasks>\n");
+    for task in p.tasks.iter() {
+        write_task(&mut out, p, task);
+    }
+    out.push_str("  </Tasks>\n");
+
+    out.push_str("  <Resources>\n");
+    for res in p.resources.iter() {
+        out.push_str("    <Resource>\n");
+        out.push_str(&format!("      <UID>{}</UID>\n", res.id + 1));
+        out.push_str(&format!("      <ID>{}</ID>\n", res.id + 1));
+        out.push_str(&format!("      <Name>{}</Name>\n", escape_xml(&res.label)));
+        out.push_str("      <Type>1</Type>\n");
+        out.push_str(&format!(
+            "      <MaxUnits>{}</MaxUnits>\n",
+            res.output / (MINUTES_PER_WEEK / 60.0)
+        ));
+        if let Some(team) = &res.team {
+            out.push_str(&format!("      <Group>{}</Group>\n", escape_xml(team)));
+        }
+        out.push_str("    </Resource>\n");
+    }
+    out.push_str("  </Resources>\n");
+
+    out.push_str("  <Assignments>\n");
+    for (number, alloc) in p.allocations.iter().enumerate() {
+        out.push_str("    <Assignment>\n");
+        out.push_str(&format!("      <UID>{}</UID>\n", number + 1));
+        out.push_str(&format!("      <TaskUID>{}</TaskUID>\n", alloc.taskid + 1));
+        out.push_str(&format!(
+            "      <ResourceUID>{}</ResourceUID>\n",
+            alloc.resourceid + 1
+        ));
+        out.push_str(&format!("      <Units>{}</Units>\n", alloc.load));
+        out.push_str("    </Assignment>\n");
+    }
+    out.push_str("  </Assignments>\n");
+
+    out.push_str("</Project>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::{self, Project, ProjectError};
+
+    use super::{format_duration, parse_duration, read_mspdi, write_mspdi, MspdiError};
+
+    const SAMPLE: &str = include_str!("../testinput_mspdi.xml");
+
+    fn assert_same_project(a: &Project, b: &Project) {
+        assert_eq!(a.tasks.len(), b.tasks.len());
+        for (x, y) in a.tasks.iter().zip(b.tasks.iter()) {
+            assert_eq!(x.id, y.id);
+            assert_eq!(x.label, y.label);
+            assert_eq!(x.duration, y.duration);
+            assert_eq!(x.earliest_start_date, y.earliest_start_date);
+            assert!((x.planned_resources - y.planned_resources).abs() < 0.001);
+            assert_eq!(x.predecessors, y.predecessors);
+        }
+        assert_eq!(a.resources.len(), b.resources.len());
+        for (x, y) in a.resources.iter().zip(b.resources.iter()) {
+            assert_eq!(x.id, y.id);
+            assert_eq!(x.label, y.label);
+            assert!((x.output - y.output).abs() < 0.001);
+            assert_eq!(x.team, y.team);
+        }
+        assert_eq!(a.allocations.len(), b.allocations.len());
+        for (x, y) in a.allocations.iter().zip(b.allocations.iter()) {
+            assert_eq!(x.taskid, y.taskid);
+            assert_eq!(x.resourceid, y.resourceid);
+            assert!((x.load - y.load).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    pub fn can_convert_durations() {
+        assert_eq!(format_duration(12.5), "PT12H30M0S");
+        assert_eq!(parse_duration("PT12H30M0S"), Some(12.5));
+        assert_eq!(parse_duration("P1DT4H"), Some(12.0));
+        assert_eq!(parse_duration("PT12"), None);
+    }
+
+    #[test]
+    pub fn can_read_sample_file() {
+        let p = read_mspdi(SAMPLE).unwrap();
+
+        // The project summary task and the blank resource are skipped.
+        assert_eq!(p.tasks.len(), 3);
+        assert_eq!(p.resources.len(), 2);
+        assert_eq!(p.allocations.len(), 4);
+
+        let implementation = &p.tasks[1];
+        assert_eq!(implementation.id, 1);
+        assert_eq!(implementation.label, "Implementation & Review");
+        assert_eq!(implementation.duration, 240);
+        assert_eq!(implementation.planned_resources, 2.0);
+        assert_eq!(implementation.earliest_start_date, date!(2023 - 06 - 12));
+        assert_eq!(implementation.predecessors, vec![0]);
+
+        assert_eq!(p.tasks[0].earliest_start_date, date!(2023 - 06 - 05));
+        assert_eq!(p.resources[1].label, "Bert");
+        assert_eq!(p.resources[1].output, 32.0);
+        assert_eq!(p.resources[1].team.as_deref(), Some("QA"));
+        assert_eq!(p.resources[0].team, None);
+        assert_eq!(p.allocations[2].load, 0.8);
+    }
+
+    #[test]
+    pub fn sample_file_survives_round_trip() {
+        let p = read_mspdi(SAMPLE).unwrap();
+        let round_tripped = read_mspdi(&write_mspdi(&p)).unwrap();
+
+        assert_same_project(&p, &round_tripped);
+    }
+
+    #[test]
+    pub fn test_project_survives_round_trip() {
+        let p = pom::read_project("testinput.json").unwrap();
+        let round_tripped = read_mspdi(&write_mspdi(&p)).unwrap();
+
+        assert_same_project(&p, &round_tripped);
+    }
+
+    #[test]
+    pub fn rejects_other_xml() {
+        assert!(read_mspdi("<html></html>").is_err());
+        assert!(read_mspdi("<Project><Tasks><Task><UID>x</UID></Task></Tasks></Project>").is_err());
+        // Implementation depending on itself
+        let xml = SAMPLE.replacen(
+            "<PredecessorUID>1</PredecessorUID>",
+            "<PredecessorUID>2</PredecessorUID>",
+            1,
+        );
+        assert!(matches!(
+            read_mspdi(&xml),
+            Err(MspdiError::Invalid(ProjectError::OwnPredecessor(_)))
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+//! Finds the days on which resources are allocated more work than they can
+//! do, and reports them as text or JSON.
+
+use serde::Serialize;
+use time::{Date, Duration};
+
+use crate::pom::{is_workday, load_percent, Project, Resource, TaskDates};
+
+/// Hours of work over the capacity that do not count as overtime, against
+/// rounding errors.
+const TOLERANCE: f32 = 0.001;
+
+#[derive(Debug, Serialize)]
+pub struct OverloadReport {
+    pub first_day: Date,
+    pub last_day: Date,
+    /// Every resource, with or without overloads.
+    pub resources: Vec<ResourceOverloads>,
+    /// The hours of work over the capacity of all resources.
+    pub overtime_hours: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceOverloads {
+    pub id: u32,
+    pub label: String,
+    pub overloads: Vec<Overload>,
+    pub overtime_hours: f32,
+}
+
+/// Days on which the resource has more work than capacity. Days off without
+/// work in between do not end it.
+#[derive(Debug, Serialize)]
+pub struct Overload {
+    pub first_day: Date,
+    pub last_day: Date,
+    /// The highest load relative to the capacity, infinite (null in JSON) if
+    /// the resource has work but no output.
+    pub peak_load: f32,
+    pub overtime_hours: f32,
+    /// The tasks the resource works on during the overload.
+    pub tasks: Vec<OverloadTask>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OverloadTask {
+    pub id: u32,
+    pub label: String,
+}
+
+impl OverloadReport {
+    pub fn has_overloads(&self) -> bool {
+        self.resources.iter().any(|x| !x.overloads.is_empty())
+    }
+}
+
+/// The first and last day of the project's tasks.
+pub fn project_span(p: &Project) -> Option<(Date, Date)> {
+    let dates = p.task_dates();
+    let first = dates.values().map(|x| x.0).min()?;
+    let last = dates.values().map(|x| x.1).max()?;
+    Some((first, last))
+}
+
+/// Compares each resource's demand, as in `Project::calculate_resource_load`,
+/// with its capacity on each day from the first to the last.
+pub fn find_overloads(p: &Project, first_day: Date, last_day: Date) -> OverloadReport {
+    let dates = p.task_dates();
+    let resources: Vec<ResourceOverloads> = p
+        .resources
+        .iter()
+        .map(|res| resource_overloads(p, &dates, res, first_day, last_day))
+        .collect();
+    OverloadReport {
+        first_day,
+        last_day,
+        overtime_hours: round_hours(resources.iter().map(|x| x.overtime_hours).sum()),
+        resources,
+    }
+}
+
+fn resource_overloads(
+    p: &Project,
+    dates: &TaskDates,
+    res: &Resource,
+    first_day: Date,
+    last_day: Date,
+) -> ResourceOverloads {
+    let mut overloads: Vec<Overload> = vec![];
+    // Whether the day continues the last overload
+    let mut continues = false;
+    let mut day = first_day;
+    while day <= last_day {
+        // Tasks pause on days off, holidays included.
+        if !is_workday(day, &p.holidays) {
+            day += Duration::days(1);
+            continue;
+        }
+        let demand = p.calculate_resource_demand(dates, res.id, day);
+        let capacity = res.capacity_on(day, &p.holidays);
+        if demand > capacity + TOLERANCE {
+            let load = p.calculate_relative_load(dates, res.id, day);
+            let tasks: Vec<OverloadTask> = p
+                .calculate_task_demands(dates, res.id, day)
+                .iter()
+                .filter_map(|(task_id, _)| p.tasks.iter().find(|x| x.id == *task_id))
+                .map(|task| OverloadTask {
+                    id: task.id,
+                    label: task.label.clone(),
+                })
+                .collect();
+            match overloads.last_mut() {
+                Some(overload) if continues => {
+                    overload.last_day = day;
+                    overload.peak_load = overload.peak_load.max(load);
+                    overload.overtime_hours += demand - capacity;
+                    for task in tasks {
+                        if !overload.tasks.contains(&task) {
+                            overload.tasks.push(task);
+                        }
+                    }
+                }
+                _ => overloads.push(Overload {
+                    first_day: day,
+                    last_day: day,
+                    peak_load: load,
+                    overtime_hours: demand - capacity,
+                    tasks,
+                }),
+            }
+            continues = true;
+        } else if demand > 0.0 || capacity > 0.0 {
+            continues = false;
+        }
+        day += Duration::days(1);
+    }
+    for overload in overloads.iter_mut() {
+        overload.overtime_hours = round_hours(overload.overtime_hours);
+    }
+    ResourceOverloads {
+        id: res.id,
+        label: res.label.clone(),
+        overtime_hours: round_hours(overloads.iter().map(|x| x.overtime_hours).sum()),
+        overloads,
+    }
+}
+
+/// Rounds to hundredths of an hour, dropping the sign of an empty sum.
+fn round_hours(hours: f32) -> f32 {
+    (hours * 100.0).round() / 100.0 + 0.0
+}
+
+/// Lists the overloads of each resource, one per line, and the overtime.
+pub fn format_overload_report(report: &OverloadReport) -> String {
+    let mut out = format!(
+        "Resource overloads from {} to {}\n",
+        report.first_day, report.last_day
+    );
+    for res in report.resources.iter() {
+        if res.overloads.is_empty() {
+            out.push_str(&format!("{}: no overloads\n", res.label));
+            continue;
+        }
+        out.push_str(&format!(
+            "{}: {}, {:.1} h overtime\n",
+            res.label,
+            overloads(res.overloads.len()),
+            res.overtime_hours
+        ));
+        for overload in res.overloads.iter() {
+            let tasks: Vec<&str> = overload.tasks.iter().map(|x| x.label.as_str()).collect();
+            out.push_str(&format!(
+                "  {} to {}  peak {}  {:.1} h overtime  {}\n",
+                overload.first_day,
+                overload.last_day,
+                load_percent(overload.peak_load),
+                overload.overtime_hours,
+                tasks.join(", ")
+            ));
+        }
+    }
+    let count: usize = report.resources.iter().map(|x| x.overloads.len()).sum();
+    out.push_str(&format!(
+        "{}, {:.1} h overtime in total\n",
+        overloads(count),
+        report.overtime_hours
+    ));
+    out
+}
+
+fn overloads(count: usize) -> String {
+    match count {
+        1 => "1 overload".to_string(),
+        _ => format!("{} overloads", count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::fixtures::{allocation, project, resource, task};
+    use crate::pom::Project;
+
+    use super::{find_overloads, format_overload_report, project_span};
+
+    /// Hans designs alone in the first week and builds with half his time
+    /// while testing in the next two.
+    fn make_project() -> Project {
+        let monday = date!(2023 - 06 - 05);
+        project(
+            vec![
+                task(0, "Design", 40, monday, vec![]),
+                task(1, "Build", 80, monday, vec![0]),
+                task(2, "Test", 80, monday, vec![0]),
+            ],
+            vec![resource(0, "Hans"), resource(1, "Ernst")],
+            vec![
+                allocation(0, 0, 1.0),
+                allocation(1, 0, 0.5),
+                allocation(2, 0, 1.0),
+                allocation(2, 1, 0.5),
+            ],
+        )
+    }
+
+    #[test]
+    pub fn finds_overloaded_days() {
+        let p = make_project();
+        let (first, last) = project_span(&p).unwrap();
+        assert_eq!(first, date!(2023 - 06 - 05));
+        let report = find_overloads(&p, first, last);
+
+        let hans = &report.resources[0];
+        // Design ends on the 12th, Build and Test work from the 13th to the
+        // 26th like calculate_resource_load counts. The weekends do not split
+        // it.
+        assert_eq!(hans.overloads.len(), 1);
+        let overload = &hans.overloads[0];
+        assert_eq!(overload.first_day, date!(2023 - 06 - 13));
+        assert_eq!(overload.last_day, date!(2023 - 06 - 26));
+        assert_eq!(overload.peak_load, 1.5);
+        let tasks: Vec<&str> = overload.tasks.iter().map(|x| x.label.as_str()).collect();
+        assert_eq!(tasks, ["Build", "Test"]);
+        // 4 h over on 10 days
+        assert_eq!(hans.overtime_hours, 40.0);
+        assert!(report.resources[1].overloads.is_empty());
+        assert_eq!(report.overtime_hours, 40.0);
+        assert!(report.has_overloads());
+
+        let text = format_overload_report(&report);
+        assert!(text.contains("Hans: 1 overload, 40.0 h overtime\n"));
+        assert!(
+            text.contains("  2023-06-13 to 2023-06-26  peak 150%  40.0 h overtime  Build, Test\n")
+        );
+        assert!(text.contains("Ernst: no overloads\n"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json["resources"][0]["overloads"][0]["first_day"],
+            "2023-06-13"
+        );
+        assert_eq!(json["overtime_hours"], 40.0);
+    }
+
+    #[test]
+    pub fn tasks_one_after_another_are_no_overload() {
+        let mut p = make_project();
+        // Hans does Design, then Build, then Test, full time.
+        p.tasks[2].predecessors = vec![1];
+        p.allocations = vec![
+            allocation(0, 0, 1.0),
+            allocation(1, 0, 1.0),
+            allocation(2, 0, 1.0),
+        ];
+        let (first, last) = project_span(&p).unwrap();
+        let report = find_overloads(&p, first, last);
+
+        assert!(!report.has_overloads());
+        assert_eq!(report.overtime_hours, 0.0);
+    }
+
+    #[test]
+    pub fn holidays_have_no_overloads() {
+        let mut p = make_project();
+        p.allocations = vec![allocation(0, 1, 0.5)];
+        p.holidays = vec![date!(2023 - 06 - 07)];
+        let report = find_overloads(&p, date!(2023 - 06 - 01), date!(2023 - 06 - 30));
+        assert!(!report.has_overloads());
+
+        // Work for a resource without any output is an overload on workdays.
+        p.resources[1].output = 0.0;
+        let report = find_overloads(&p, date!(2023 - 06 - 01), date!(2023 - 06 - 30));
+        let ernst = &report.resources[1];
+        assert_eq!(ernst.overloads.len(), 1);
+        assert_eq!(ernst.overloads[0].first_day, date!(2023 - 06 - 06));
+        assert_eq!(ernst.overloads[0].peak_load, f32::INFINITY);
+        assert!(format_overload_report(&report).contains("peak no capacity"));
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json["resources"][1]["overloads"][0]["peak_load"].is_null());
+
+        p.allocations.clear();
+        assert!(!find_overloads(&p, date!(2023 - 06 - 01), date!(2023 - 06 - 30)).has_overloads());
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use crate::pom::{Project, Task};
+
+/// PlantUML identifies tasks by their name, so labels that occur more than
+/// once get their id appended to keep them apart.
+fn task_names(p: &Project) -> HashMap<u32, String> {
+    let mut label_count = HashMap::<&str, u32>::new();
+    for task in p.tasks.iter() {
+        *label_count.entry(task.label.as_str()).or_insert(0) += 1;
+    }
+
+    p.tasks
+        .iter()
+        .map(|task| {
+            let label = task.label.replace(['[', ']'], "");
+            let name = if label_count[task.label.as_str()] > 1 {
+                format!("{} ({})", label, task.id)
+            } else {
+                label
+            };
+            (task.id, name)
+        })
+        .collect()
+}
+
+fn task_alias(task: &Task) -> String {
+    format!("T{}", task.id)
+}
+
+/// The number of working days the task lasts, rounded up the same way
+/// `Task::get_end_date` counts them.
+fn task_duration_days(task: &Task) -> u32 {
+    task.get_work_days().ceil() as u32
+}
+
+fn resource_assignments(p: &Project, task: &Task) -> String {
+    let mut assignments = String::new();
+    for alloc in p.allocations.iter().filter(|x| x.taskid == task.id) {
+        let res = p.get_resource_by_id(alloc.resourceid);
+        assignments.push_str(&format!(
+            " {{{}:{}%}}",
+            res.label.replace(['{', '}', ':'], ""),
+            (alloc.load * 100.0).round()
+        ));
+    }
+    if !assignments.is_empty() {
+        assignments.insert_str(0, " on");
+    }
+    assignments
+}
+
+/// Exports the project as a PlantUML `@startgantt` diagram.
+pub fn export_plantuml(p: &Project) -> String {
+    let names = task_names(p);
+    let mut out = String::from("@startgantt\n");
+
+    if let Some(project_start) = p.tasks.iter().map(|x| x.get_actual_start_date(p)).min() {
+        out.push_str(&format!("Project starts {}\n", project_start));
+    }
+    out.push_str("saturday are closed\n");
+    out.push_str("sunday are closed\n\n");
+
+    for task in p.tasks.iter() {
+        out.push_str(&format!(
+            "[{}] as [{}]{} lasts {} days\n",
+            names[&task.id],
+            task_alias(task),
+            resource_assignments(p, task),
+            task_duration_days(task)
+        ));
+    }
+    out.push('\n');
+
+    for task in p.tasks.iter() {
+        let predecessors: Vec<&Task> = task
+            .predecessors
+            .iter()
+            .filter_map(|pred_id| p.tasks.iter().find(|x| x.id == *pred_id))
+            .collect();
+
+        if predecessors.is_empty() {
+            out.push_str(&format!(
+                "[{}] starts {}\n",
+                task_alias(task),
+                task.earliest_start_date
+            ));
+        }
+        for pred in predecessors {
+            out.push_str(&format!(
+                "[{}] starts at [{}]'s end\n",
+                task_alias(task),
+                task_alias(pred)
+            ));
+        }
+    }
+
+    out.push_str("@endgantt\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::fixtures::{allocation, project, resource, task};
+    use crate::pom::Project;
+
+    use super::export_plantuml;
+
+    fn make_project() -> Project {
+        let start = date!(2023 - 06 - 01);
+        project(
+            vec![
+                task(0, "Design", 80, start, vec![]),
+                task(1, "Build", 80, start, vec![0]),
+                task(2, "Build", 80, start, vec![0, 1]),
+            ],
+            vec![resource(0, "Hans")],
+            vec![allocation(0, 0, 0.75)],
+        )
+    }
+
+    #[test]
+    pub fn exports_tasks_with_durations_and_resources() {
+        let out = export_plantuml(&make_project());
+
+        assert!(out.starts_with("@startgantt\n"));
+        assert!(out.ends_with("@endgantt\n"));
+        assert!(out.contains("Project starts 2023-06-01\n"));
+        assert!(out.contains("saturday are closed\nsunday are closed\n"));
+        assert!(out.contains("[Design] as [T0] on {Hans:75%} lasts 10 days\n"));
+    }
+
+    #[test]
+    pub fn exports_predecessors_as_end_links() {
+        let out = export_plantuml(&make_project());
+
+        assert!(out.contains("[T0] starts 2023-06-01\n"));
+        assert!(out.contains("[T1] starts at [T0]'s end\n"));
+        assert!(out.contains("[T2] starts at [T0]'s end\n[T2] starts at [T1]'s end\n"));
+    }
+
+    #[test]
+    pub fn disambiguates_duplicate_labels() {
+        let out = export_plantuml(&make_project());
+
+        assert!(out.contains("[Build (1)] as [T1] lasts 10 days\n"));
+        assert!(out.contains("[Build (2)] as [T2] lasts 10 days\n"));
+    }
+}
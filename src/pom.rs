@@ -1,4 +1,8 @@
-use std::{collections::VecDeque, fs::File};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, fs,
+    path::Path,
+};
 
 use serde::{Deserialize, Serialize};
 use time::{Date, Duration, Weekday};
@@ -17,6 +21,25 @@ pub struct Task {
     pub allocated_resources: f32,
 
     pub predecessors: Vec<u32>,
+
+    /// Free-form categories like a team or component, used to colour the bar.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// The colour of the bar, overriding any colour picked by tag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    /// The FTE needed per role, to staff the task before the people are
+    /// known. Independent of `planned_resources`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub role_demand: Vec<RoleDemand>,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
+pub struct RoleDemand {
+    pub role: String,
+    pub fte: f32,
 }
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
@@ -24,6 +47,14 @@ pub struct Resource {
     pub id: u32,
     pub label: String,
     pub output: f32,
+
+    /// The team or group the resource belongs to, to plan by team.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+
+    /// The roles or skills the resource can fill, like "backend" or "qa".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
@@ -38,15 +69,228 @@ pub struct Project {
     pub tasks: Vec<Task>,
     pub resources: Vec<Resource>,
     pub allocations: Vec<Allocation>,
+
+    /// Public holidays and other days nobody works on.
+    #[serde(default)]
+    pub holidays: Vec<Date>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProjectFormat {
+    /// JSON, optionally with `//` and `/* */` comments and trailing commas
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ProjectFormat {
+    /// Picks the format by file extension, anything unknown is read as JSON.
+    pub fn from_file_name(file_name: &str) -> ProjectFormat {
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match extension.as_str() {
+            "yaml" | "yml" => ProjectFormat::Yaml,
+            "toml" => ProjectFormat::Toml,
+            _ => ProjectFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ProjectFileError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Yaml(serde_yaml::Error),
+    Toml(toml::de::Error),
+    Invalid(ProjectError),
+}
+
+impl fmt::Display for ProjectFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectFileError::Io(e) => write!(f, "{}", e),
+            ProjectFileError::Json(e) => write!(f, "Not a valid JSON project: {}", e),
+            ProjectFileError::Yaml(e) => write!(f, "Not a valid YAML project: {}", e),
+            ProjectFileError::Toml(e) => write!(f, "Not a valid TOML project: {}", e),
+            ProjectFileError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// A project that cannot be scheduled.
+#[derive(Debug, PartialEq)]
+pub enum ProjectError {
+    /// The task, by id, does not plan for more than 0 resources.
+    PlannedResources(u32),
+    /// The task, by id, lists itself as predecessor.
+    OwnPredecessor(u32),
+    /// The task, by id, is among the predecessors of its predecessors.
+    PredecessorCycle(u32),
+    /// The allocation at the index has a negative load.
+    NegativeLoad {
+        index: usize,
+        taskid: u32,
+        resourceid: u32,
+    },
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::PlannedResources(id) => {
+                write!(f, "Task {} needs planned resources above 0", id)
+            }
+            ProjectError::OwnPredecessor(id) => write!(f, "Task {} is its own predecessor", id),
+            ProjectError::PredecessorCycle(id) => {
+                write!(f, "The predecessors of task {} form a cycle", id)
+            }
+            ProjectError::NegativeLoad {
+                taskid, resourceid, ..
+            } => write!(
+                f,
+                "The load of resource {} on task {} is negative",
+                resourceid, taskid
+            ),
+        }
+    }
 }
 
-pub fn load_project(file_name: &str) -> Project {
-    let reader = File::open(file_name);
-    if let Ok(file) = reader {
-        let p: Project = serde_json::from_reader(file).unwrap();
-        return p;
+/// Checks the tasks in order for what would break scheduling them.
+pub fn validate_tasks(tasks: &[Task]) -> Result<(), ProjectError> {
+    for task in tasks.iter() {
+        if task.planned_resources <= 0.0 {
+            return Err(ProjectError::PlannedResources(task.id));
+        }
+        if task.predecessors.contains(&task.id) {
+            return Err(ProjectError::OwnPredecessor(task.id));
+        }
+        if depends_on_itself(tasks, task) {
+            return Err(ProjectError::PredecessorCycle(task.id));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the task is among the predecessors of its predecessors.
+fn depends_on_itself(tasks: &[Task], task: &Task) -> bool {
+    let mut visited: Vec<u32> = vec![];
+    let mut open = task.predecessors.clone();
+    while let Some(id) = open.pop() {
+        if id == task.id {
+            return true;
+        }
+        if visited.contains(&id) {
+            continue;
+        }
+        visited.push(id);
+        if let Some(pred) = tasks.iter().find(|x| x.id == id) {
+            open.extend(pred.predecessors.iter());
+        }
+    }
+    false
+}
+
+pub fn validate_allocations(allocations: &[Allocation]) -> Result<(), ProjectError> {
+    match allocations.iter().position(|x| x.load < 0.0) {
+        Some(index) => Err(ProjectError::NegativeLoad {
+            index,
+            taskid: allocations[index].taskid,
+            resourceid: allocations[index].resourceid,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Removes `//` and `/* */` comments and trailing commas so that commented
+/// JSON (JSONC) can be handed to serde_json. Strings are left untouched.
+pub(crate) fn strip_json_extensions(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut pending_comma = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                if pending_comma {
+                    out.push(',');
+                    pending_comma = false;
+                }
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                out.push(escaped);
+                            }
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                out.push(' ');
+            }
+            // Commas are held back until we know whether the next token
+            // closes an object or array.
+            ',' => {
+                if pending_comma {
+                    out.push(',');
+                }
+                pending_comma = true;
+            }
+            c if c.is_whitespace() => out.push(c),
+            c => {
+                if pending_comma && c != '}' && c != ']' {
+                    out.push(',');
+                }
+                pending_comma = false;
+                out.push(c);
+            }
+        }
+    }
+    if pending_comma {
+        out.push(',');
     }
-    panic!("Not a valid project file!")
+    out
+}
+
+pub fn parse_project(content: &str, format: ProjectFormat) -> Result<Project, ProjectFileError> {
+    let project: Project = match format {
+        ProjectFormat::Json => {
+            serde_json::from_str(&strip_json_extensions(content)).map_err(ProjectFileError::Json)
+        }
+        ProjectFormat::Yaml => serde_yaml::from_str(content).map_err(ProjectFileError::Yaml),
+        ProjectFormat::Toml => toml::from_str(content).map_err(ProjectFileError::Toml),
+    }?;
+    project.validate().map_err(ProjectFileError::Invalid)?;
+    Ok(project)
+}
+
+/// Reads a project file, the format is chosen by the file's extension.
+pub fn read_project(file_name: &str) -> Result<Project, ProjectFileError> {
+    let content = fs::read_to_string(file_name).map_err(ProjectFileError::Io)?;
+    parse_project(&content, ProjectFormat::from_file_name(file_name))
 }
 
 pub const GENERIC_RESOURCE_OUTPUT: f32 = 8.0;
@@ -65,46 +309,52 @@ impl Task {
         }
     }
 
+    /// Milestones are tasks without any effort, they start and end on the same day.
+    pub fn is_milestone(&self) -> bool {
+        self.duration == 0
+    }
+
     /// Returns the number of working days this task
     pub fn get_work_days(&self) -> f32 {
         self.duration as f32 / self.get_work_force()
     }
 
-    /// Returns the end date given the assigned resources.
-    pub fn get_end_date(&self) -> Date {
+    /// Returns the end date given the assigned resources, if the task starts
+    /// on its earliest start date.
+    pub fn get_end_date(&self, holidays: &[Date]) -> Date {
+        self.get_end_date_from(self.earliest_start_date, holidays)
+    }
+
+    /// Returns the end date if the task starts on the given day. Only working
+    /// days, see `is_workday`, count.
+    fn get_end_date_from(&self, start_date: Date, holidays: &[Date]) -> Date {
         let mut remaining_days = self.get_work_days();
-        let mut current_date = self.earliest_start_date;
+        let mut current_date = start_date;
         while remaining_days > 0.0 {
             if let Some(_current_date) = current_date.checked_add(Duration::days(1)) {
                 current_date = _current_date;
             } else {
                 print!("FAILED {}", current_date);
             }
-            let wd = current_date.weekday();
-            if ![Weekday::Saturday, Weekday::Sunday].contains(&wd) {
+            if is_workday(current_date, holidays) {
                 remaining_days -= 1.0;
             }
         }
         current_date
     }
 
-    // returns the number of calendar days between start and end
-    pub fn get_calendar_days(&self) -> u32 {
-        let end = self.get_end_date();
-        let dur = end - self.earliest_start_date;
-        dur.whole_days() as u32
-    }
-
-    pub fn get_days_remaining_at(&self, reference_date: Date, start_date: Date) -> u32 {
+    pub fn get_days_remaining_at(
+        &self,
+        reference_date: Date,
+        start_date: Date,
+        holidays: &[Date],
+    ) -> u32 {
         let mut remaining_days = self.get_work_days();
         let mut current_date = start_date;
 
-        println!("RefDate {}, StartDate {}", reference_date, start_date);
-
         while remaining_days > 0.0 && current_date <= reference_date {
             current_date = current_date.checked_add(Duration::days(1)).unwrap();
-            let wd = current_date.weekday();
-            if ![Weekday::Saturday, Weekday::Sunday].contains(&wd) {
+            if is_workday(current_date, holidays) {
                 remaining_days -= 1.0;
             }
         }
@@ -120,14 +370,10 @@ impl Task {
 
     pub fn get_actual_end_date(&self, proj: &Project) -> Date {
         if self.predecessors.is_empty() {
-            return self.get_end_date();
+            return self.get_end_date(&proj.holidays);
         }
 
-        let actual_start_date = self.get_actual_start_date(proj);
-        let calendar_days = self.get_calendar_days();
-        actual_start_date
-            .checked_add(Duration::days(calendar_days as i64))
-            .unwrap()
+        self.get_end_date_from(self.get_actual_start_date(proj), &proj.holidays)
     }
 
     pub fn get_actual_start_date(&self, proj: &Project) -> Date {
@@ -148,40 +394,75 @@ impl Task {
         the_start_date
     }
 
+    /// The actual start and end date of the task, looking up and filling in
+    /// those of its predecessors in the dates.
+    fn get_actual_dates(&self, proj: &Project, dates: &mut TaskDates) -> (Date, Date) {
+        if let Some(known) = dates.get(&self.id) {
+            return *known;
+        }
+        let actual = if self.predecessors.is_empty() {
+            (self.earliest_start_date, self.get_end_date(&proj.holidays))
+        } else {
+            let mut the_start_date = self.earliest_start_date;
+            for pred_id in self.predecessors.iter() {
+                if let Some(predecessor_task) = proj.tasks.iter().find(|x| x.id == *pred_id) {
+                    let (_, pred_end_date) = predecessor_task.get_actual_dates(proj, dates);
+                    the_start_date = the_start_date.max(pred_end_date);
+                }
+            }
+            (
+                the_start_date,
+                self.get_end_date_from(the_start_date, &proj.holidays),
+            )
+        };
+        dates.insert(self.id, actual);
+        actual
+    }
+
+    /// Whether the predecessor is the one whose end determines the start of
+    /// this task, i.e. any delay of it delays this task.
+    pub fn is_driven_by(&self, proj: &Project, predecessor_id: u32) -> bool {
+        proj.tasks
+            .iter()
+            .find(|x| x.id == predecessor_id)
+            .is_some_and(|pred| pred.get_actual_end_date(proj) == self.get_actual_start_date(proj))
+    }
+
     /// Calculates the actually remaining (duration!) days of the task relative to a given date,
     /// including the dependencies of the task.
     pub fn get_actual_remaining_work_days(&self, proj: &Project, reference_date: Date) -> u32 {
         let start = self.get_actual_start_date(proj);
-        self.get_days_remaining_at(reference_date, start)
+        self.get_days_remaining_at(reference_date, start, &proj.holidays)
     }
+}
 
-    /// Calculates the actually remaining calender days of the task relative to a given date,
-    /// including the dependencies of the task.
-    pub fn get_actual_remaining_calender_days(&self, proj: &Project, reference_date: Date) -> u32 {
-        let start = self.get_actual_start_date(proj);
-        let end = self.get_actual_end_date(proj);
+/// The actual start and end date of each task by id.
+pub type TaskDates = HashMap<u32, (Date, Date)>;
 
-        if reference_date > end {
-            return 0;
-        }
+impl Project {
+    /// Checks for what would break scheduling, like predecessor cycles.
+    pub fn validate(&self) -> Result<(), ProjectError> {
+        validate_tasks(&self.tasks)?;
+        validate_allocations(&self.allocations)
+    }
 
-        if start < reference_date && reference_date <= end {
-            return (end - reference_date).whole_days() as u32;
-        } else if start > reference_date {
-            return (end - start).whole_days() as u32;
+    /// The actual start and end date of every task. Computing them once is
+    /// much cheaper than asking each task, which walks its predecessors again.
+    pub fn task_dates(&self) -> TaskDates {
+        let mut dates = TaskDates::new();
+        for task in self.tasks.iter() {
+            task.get_actual_dates(self, &mut dates);
         }
-
-        0
+        dates
     }
-}
 
-impl Project {
+    /// Returns a copy of the resource, panics if there is none with the id.
     pub fn get_resource_by_id(&self, resource_id: u32) -> Resource {
         let res = self
             .resources
             .iter()
             .find(|x| x.id == resource_id)
-            .expect(&format!("Need resource with correct id! {}", resource_id));
+            .unwrap_or_else(|| panic!("Need resource with correct id! {}", resource_id));
         res.clone()
     }
 
@@ -204,31 +485,227 @@ impl Project {
         }
     }
 
-    pub fn calculate_resource_load(&self, resource_id: u32, day: Date) -> f32 {
+    /// Returns the ids of the tasks on the critical path: the tasks ending
+    /// last and, transitively, the predecessors driving their start.
+    pub fn critical_path(&self) -> HashSet<u32> {
+        let mut critical = HashSet::new();
+        let Some(project_end) = self.tasks.iter().map(|x| x.get_actual_end_date(self)).max() else {
+            return critical;
+        };
+        let mut pending: Vec<&Task> = self
+            .tasks
+            .iter()
+            .filter(|x| x.get_actual_end_date(self) == project_end)
+            .collect();
+        while let Some(task) = pending.pop() {
+            if !critical.insert(task.id) {
+                continue;
+            }
+            for pred_id in task.predecessors.iter() {
+                if task.is_driven_by(self, *pred_id) {
+                    if let Some(pred) = self.tasks.iter().find(|x| x.id == *pred_id) {
+                        pending.push(pred);
+                    }
+                }
+            }
+        }
+        critical
+    }
+
+    /// The FTE allocated to the resource on the day. Like `get_end_date`
+    /// counts them, a task's days of work follow its start day up to and
+    /// including its end day, so a successor starting on the end day of its
+    /// predecessor does not overlap it.
+    pub fn calculate_resource_load(&self, dates: &TaskDates, resource_id: u32, day: Date) -> f32 {
         let mut sum = 0.0;
         for relevant_allocation in self
             .allocations
             .iter()
             .filter(|x| x.resourceid == resource_id)
         {
-            let task = self
-                .tasks
-                .iter()
-                .find(|x| x.id == relevant_allocation.taskid)
+            let (start_date, end_date) = dates
+                .get(&relevant_allocation.taskid)
                 .expect("Resource Allocation does not apply to any existing task!");
-            let start_date = task.get_actual_start_date(self);
-            let end_date = task.get_actual_end_date(self);
-            if day >= start_date && day <= end_date {
+            if day > *start_date && day <= *end_date {
                 sum += relevant_allocation.load;
             }
         }
         sum
     }
+
+    /// The hours each task allocated to the resource needs from it on the day,
+    /// as task id and hours. Tasks are worked on on working days, counting
+    /// their days like `calculate_resource_load`.
+    pub fn calculate_task_demands(
+        &self,
+        dates: &TaskDates,
+        resource_id: u32,
+        day: Date,
+    ) -> Vec<(u32, f32)> {
+        if !is_workday(day, &self.holidays) {
+            return vec![];
+        }
+        self.allocations
+            .iter()
+            .filter(|x| x.resourceid == resource_id && x.load > 0.0)
+            .filter_map(|allocation| {
+                let (start, end) = dates.get(&allocation.taskid)?;
+                let running = day > *start && day <= *end;
+                running.then_some((allocation.taskid, allocation.load * GENERIC_RESOURCE_OUTPUT))
+            })
+            .collect()
+    }
+
+    /// The hours of work allocated to the resource on the day.
+    pub fn calculate_resource_demand(&self, dates: &TaskDates, resource_id: u32, day: Date) -> f32 {
+        if is_workday(day, &self.holidays) {
+            self.calculate_resource_load(dates, resource_id, day) * GENERIC_RESOURCE_OUTPUT
+        } else {
+            0.0
+        }
+    }
+
+    /// The demand on the resource relative to its capacity on the day. Work
+    /// for a resource without any output is an infinite load.
+    pub fn calculate_relative_load(&self, dates: &TaskDates, resource_id: u32, day: Date) -> f32 {
+        let demand = self.calculate_resource_demand(dates, resource_id, day);
+        let capacity = self
+            .get_resource_by_id(resource_id)
+            .capacity_on(day, &self.holidays);
+        match (demand, capacity) {
+            (demand, _) if demand <= 0.0 => 0.0,
+            (_, capacity) if capacity <= 0.0 => f32::INFINITY,
+            (demand, capacity) => demand / capacity,
+        }
+    }
+
+    /// The teams of the resources, in the order they first appear.
+    pub fn teams(&self) -> Vec<&str> {
+        let mut teams: Vec<&str> = vec![];
+        for team in self.resources.iter().filter_map(|x| x.team.as_deref()) {
+            if !teams.contains(&team) {
+                teams.push(team);
+            }
+        }
+        teams
+    }
+
+    /// The resources of the team, or those in no team for None.
+    pub fn team_members(&self, team: Option<&str>) -> Vec<&Resource> {
+        self.resources
+            .iter()
+            .filter(|x| x.team.as_deref() == team)
+            .collect()
+    }
+
+    /// The hours of work allocated to the team's members on the day.
+    pub fn calculate_team_demand(&self, dates: &TaskDates, team: Option<&str>, day: Date) -> f32 {
+        self.team_members(team)
+            .iter()
+            .map(|x| self.calculate_resource_demand(dates, x.id, day))
+            .sum()
+    }
+
+    /// The hours the team's members work on the day.
+    pub fn team_capacity_on(&self, team: Option<&str>, day: Date) -> f32 {
+        self.team_members(team)
+            .iter()
+            .map(|x| x.capacity_on(day, &self.holidays))
+            .sum()
+    }
+}
+
+/// A load as a rounded percentage, of the capacity for relative loads or of
+/// a full time resource for allocations.
+pub fn load_percent(load: f32) -> String {
+    if load.is_finite() {
+        format!("{}%", (load * 100.0).round())
+    } else {
+        "no capacity".to_string()
+    }
+}
+
+/// Whether tasks make progress on the day, which they do from Monday to Friday
+/// except on holidays.
+pub fn is_workday(day: Date, holidays: &[Date]) -> bool {
+    ![Weekday::Saturday, Weekday::Sunday].contains(&day.weekday()) && !holidays.contains(&day)
+}
+
+impl Resource {
+    /// The hours the resource works on the day: a fifth of its weekly output
+    /// on weekdays that are no holidays.
+    pub fn capacity_on(&self, day: Date, holidays: &[Date]) -> f32 {
+        if is_workday(day, holidays) {
+            self.output / 5.0
+        } else {
+            0.0
+        }
+    }
 }
 
+/// Builders for the tests of the exports and reports.
 #[cfg(test)]
-mod tests {
+pub(crate) mod fixtures {
+    use time::Date;
+
     use super::{Allocation, Project, Resource, Task};
+
+    /// A task for one resource starting on the day at the earliest.
+    pub fn task(id: u32, label: &str, duration: u32, start: Date, predecessors: Vec<u32>) -> Task {
+        Task {
+            id,
+            duration,
+            label: label.to_string(),
+            earliest_start_date: start,
+            planned_resources: 1.0,
+            allocated_resources: 0.0,
+            predecessors,
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
+        }
+    }
+
+    /// A full-time resource in no team and without roles.
+    pub fn resource(id: u32, label: &str) -> Resource {
+        Resource {
+            id,
+            label: label.to_string(),
+            output: 40.0,
+            team: None,
+            roles: vec![],
+        }
+    }
+
+    pub fn allocation(taskid: u32, resourceid: u32, load: f32) -> Allocation {
+        Allocation {
+            taskid,
+            resourceid,
+            load,
+        }
+    }
+
+    /// A project without holidays.
+    pub fn project(
+        tasks: Vec<Task>,
+        resources: Vec<Resource>,
+        allocations: Vec<Allocation>,
+    ) -> Project {
+        Project {
+            tasks,
+            resources,
+            allocations,
+            holidays: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_project, read_project, strip_json_extensions, Allocation, Project, ProjectError,
+        ProjectFormat, Resource, Task,
+    };
     use time::macros::date;
 
     fn make_simple_task(duration: u32, planned_resources: f32) -> Task {
@@ -239,6 +716,9 @@ mod tests {
             earliest_start_date: date!(2023 - 02 - 01),
             planned_resources,
             predecessors: vec![],
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
             allocated_resources: 0.0,
         }
     }
@@ -254,7 +734,7 @@ mod tests {
     pub fn can_calculate_end_date() {
         let mut t = make_simple_task(80, 2.0);
         t.earliest_start_date = date!(2023 - 06 - 08);
-        let end = t.get_end_date();
+        let end = t.get_end_date(&[]);
 
         // Should boil down to 5 workdays, however, since
         // 08/06 is a Thursday we have a weekend in between, which
@@ -262,6 +742,36 @@ mod tests {
         assert_eq!(end, date!(2023 - 06 - 15))
     }
 
+    #[test]
+    pub fn holidays_move_the_end_date() {
+        let mut t = make_simple_task(80, 2.0);
+        t.earliest_start_date = date!(2023 - 06 - 08);
+        let holidays = [date!(2023 - 06 - 12)];
+        assert_eq!(t.get_end_date(&holidays), date!(2023 - 06 - 16));
+
+        let mut project = make_project();
+        project.tasks[0] = t;
+        project.tasks[1].predecessors = vec![0];
+        project.holidays = holidays.to_vec();
+        // The successor starts a workday later too.
+        assert_eq!(
+            project.tasks[0].get_actual_end_date(&project),
+            date!(2023 - 06 - 16)
+        );
+        assert_eq!(
+            project.tasks[1].get_actual_start_date(&project),
+            date!(2023 - 06 - 16)
+        );
+        assert_eq!(
+            project.tasks[0].get_days_remaining_at(
+                date!(2023 - 06 - 12),
+                date!(2023 - 06 - 08),
+                &holidays
+            ),
+            3
+        );
+    }
+
     // #[test]
     // pub fn can_get_remainder()
     // {
@@ -276,6 +786,7 @@ mod tests {
             resources: vec![],
             tasks: vec![],
             allocations: vec![],
+            holidays: vec![],
         };
 
         let t0 = Task {
@@ -285,6 +796,9 @@ mod tests {
             earliest_start_date: date!(2023 - 06 - 01),
             planned_resources: 1.0,
             predecessors: vec![],
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
             allocated_resources: 0.0,
         };
 
@@ -295,6 +809,9 @@ mod tests {
             earliest_start_date: date!(2023 - 06 - 01),
             planned_resources: 1.0,
             predecessors: vec![0],
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
             allocated_resources: 0.0,
         };
 
@@ -310,6 +827,7 @@ mod tests {
             resources: vec![],
             tasks: vec![],
             allocations: vec![],
+            holidays: vec![],
         };
 
         let t0 = Task {
@@ -319,6 +837,9 @@ mod tests {
             earliest_start_date: date!(2023 - 06 - 01),
             planned_resources: 1.0,
             predecessors: vec![],
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
             allocated_resources: 0.0,
         };
 
@@ -329,6 +850,9 @@ mod tests {
             earliest_start_date: date!(2023 - 06 - 01),
             planned_resources: 1.0,
             predecessors: vec![0],
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
             allocated_resources: 0.0,
         };
 
@@ -346,6 +870,9 @@ mod tests {
             earliest_start_date: date!(2023 - 06 - 01),
             planned_resources: 1.0,
             predecessors: vec![],
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
             allocated_resources: 0.0,
         };
 
@@ -356,15 +883,67 @@ mod tests {
             earliest_start_date: date!(2023 - 06 - 03),
             planned_resources: 1.0,
             predecessors: vec![],
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
             allocated_resources: 0.0,
         };
 
-        let proj = Project {
+        Project {
             resources: vec![],
             tasks: vec![t0, t1],
             allocations: vec![],
-        };
-        proj
+            holidays: vec![],
+        }
+    }
+
+    #[test]
+    pub fn successors_count_working_days_from_their_actual_start() {
+        let mut project = make_project();
+        // Three days from Monday the 5th, but First ends on Thursday the 8th.
+        project.tasks[1].duration = 24;
+        project.tasks[1].earliest_start_date = date!(2023 - 06 - 05);
+        project.tasks[1].predecessors = vec![0];
+        let second = &project.tasks[1];
+        assert_eq!(
+            second.get_actual_start_date(&project),
+            date!(2023 - 06 - 08)
+        );
+
+        // Adding the three calendar days of the planned span ended it on
+        // Sunday the 11th. Counting working days pushes it past the weekend.
+        assert_eq!(second.get_end_date(&[]), date!(2023 - 06 - 08));
+        assert_eq!(second.get_actual_end_date(&project), date!(2023 - 06 - 13));
+        assert_eq!(
+            project.task_dates()[&1],
+            (date!(2023 - 06 - 08), date!(2023 - 06 - 13))
+        );
+    }
+
+    #[test]
+    pub fn finds_driving_predecessors_and_critical_path() {
+        let mut project = make_project();
+        // Third waits for both, First ends on 06-08 and Second on 06-09.
+        project.tasks.push(Task {
+            id: 2,
+            duration: 40,
+            label: "Third".to_string(),
+            earliest_start_date: date!(2023 - 06 - 01),
+            planned_resources: 1.0,
+            predecessors: vec![0, 1],
+            tags: vec![],
+            color: None,
+            role_demand: vec![],
+            allocated_resources: 0.0,
+        });
+        let third = &project.tasks[2];
+
+        assert!(!third.is_driven_by(&project, 0));
+        assert!(third.is_driven_by(&project, 1));
+        assert!(!third.is_driven_by(&project, 7));
+        let mut critical: Vec<u32> = project.critical_path().into_iter().collect();
+        critical.sort();
+        assert_eq!(critical, vec![1, 2]);
     }
 
     #[test]
@@ -374,6 +953,8 @@ mod tests {
             id: 0,
             label: "r1".to_string(),
             output: 40.0,
+            team: None,
+            roles: vec![],
         };
 
         project.resources.push(r);
@@ -391,10 +972,209 @@ mod tests {
         project.allocations.push(a);
         project.allocations.push(b);
 
-        let load = project.calculate_resource_load(0, date!(2023 - 06 - 01));
+        let dates = project.task_dates();
+        // Work starts on the day after the start date.
+        assert_eq!(
+            0.0,
+            project.calculate_resource_load(&dates, 0, date!(2023 - 06 - 01))
+        );
+        let load = project.calculate_resource_load(&dates, 0, date!(2023 - 06 - 02));
         assert_eq!(0.8, load);
 
-        let load2 = project.calculate_resource_load(0, date!(2023 - 06 - 03));
+        let load2 = project.calculate_resource_load(&dates, 0, date!(2023 - 06 - 05));
         assert_eq!(1.3, load2);
     }
+
+    #[test]
+    pub fn can_add_up_teams() {
+        let mut project = make_project();
+        for (id, team) in [(0, Some("Apps")), (1, None), (2, Some("Apps"))] {
+            project.resources.push(Resource {
+                id,
+                label: format!("r{}", id),
+                output: 40.0,
+                team: team.map(|x| x.to_string()),
+                roles: vec![],
+            });
+            project.allocations.push(Allocation {
+                taskid: 0,
+                resourceid: id,
+                load: 0.5,
+            });
+        }
+
+        assert_eq!(project.teams(), vec!["Apps"]);
+        assert_eq!(project.team_members(None).len(), 1);
+        let dates = project.task_dates();
+        let friday = date!(2023 - 06 - 02);
+        assert_eq!(
+            project.calculate_team_demand(&dates, Some("Apps"), friday),
+            8.0
+        );
+        assert_eq!(project.team_capacity_on(Some("Apps"), friday), 16.0);
+        assert_eq!(project.calculate_team_demand(&dates, None, friday), 4.0);
+        assert_eq!(
+            project.team_capacity_on(Some("Apps"), date!(2023 - 06 - 03)),
+            0.0
+        );
+    }
+
+    #[test]
+    pub fn can_calculate_load_relative_to_capacity() {
+        let mut project = make_project();
+        project.resources.push(Resource {
+            id: 0,
+            label: "r1".to_string(),
+            output: 32.0,
+            team: None,
+            roles: vec![],
+        });
+        project.allocations.push(Allocation {
+            taskid: 0,
+            resourceid: 0,
+            load: 0.8,
+        });
+        project.holidays.push(date!(2023 - 06 - 02));
+
+        let dates = project.task_dates();
+        // 6.4 of 6.4 hours on Monday the 5th
+        assert_eq!(
+            project
+                .calculate_task_demands(&dates, 0, date!(2023 - 06 - 05))
+                .len(),
+            1
+        );
+        assert_eq!(
+            project.calculate_resource_demand(&dates, 0, date!(2023 - 06 - 05)),
+            6.4
+        );
+        assert_eq!(
+            project.calculate_relative_load(&dates, 0, date!(2023 - 06 - 05)),
+            1.0
+        );
+        // The task pauses on the holiday and the weekend.
+        assert!(project
+            .calculate_task_demands(&dates, 0, date!(2023 - 06 - 02))
+            .is_empty());
+        assert_eq!(
+            project.calculate_relative_load(&dates, 0, date!(2023 - 06 - 02)),
+            0.0
+        );
+        assert_eq!(
+            project.calculate_resource_demand(&dates, 0, date!(2023 - 06 - 03)),
+            0.0
+        );
+        assert_eq!(
+            project.calculate_relative_load(&dates, 0, date!(2023 - 06 - 03)),
+            0.0
+        );
+        // Work for a resource without any output
+        project.resources[0].output = 0.0;
+        assert_eq!(
+            project.calculate_relative_load(&dates, 0, date!(2023 - 06 - 05)),
+            f32::INFINITY
+        );
+    }
+
+    #[test]
+    pub fn can_strip_json_comments_and_trailing_commas() {
+        let json = r#"{
+            "a": 1, // the "a"
+            /* "b": 2, */
+            "c": "http://x/*y*/",
+            "d": [1, 2,],
+        }"#;
+        let value: serde_json::Value = serde_json::from_str(&strip_json_extensions(json)).unwrap();
+
+        assert_eq!(value["a"], 1);
+        assert!(value.get("b").is_none());
+        assert_eq!(value["c"], "http://x/*y*/");
+        assert_eq!(value["d"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    pub fn can_detect_format_by_extension() {
+        assert_eq!(
+            ProjectFormat::from_file_name("plan.yml"),
+            ProjectFormat::Yaml
+        );
+        assert_eq!(
+            ProjectFormat::from_file_name("plan.YAML"),
+            ProjectFormat::Yaml
+        );
+        assert_eq!(
+            ProjectFormat::from_file_name("plan.toml"),
+            ProjectFormat::Toml
+        );
+        assert_eq!(
+            ProjectFormat::from_file_name("plan.jsonc"),
+            ProjectFormat::Json
+        );
+        assert_eq!(ProjectFormat::from_file_name("plan"), ProjectFormat::Json);
+    }
+
+    #[test]
+    pub fn yaml_and_toml_projects_match_json() {
+        let json = read_project("testinput.json").unwrap();
+        for file_name in ["testinput.yaml", "testinput.toml"] {
+            let other = read_project(file_name).unwrap();
+            assert_eq!(
+                serde_json::to_value(&json).unwrap(),
+                serde_json::to_value(&other).unwrap(),
+                "{}",
+                file_name
+            );
+        }
+    }
+
+    #[test]
+    pub fn reports_invalid_projects() {
+        assert!(parse_project("tasks: 5", ProjectFormat::Yaml).is_err());
+        assert!(parse_project("tasks = 5", ProjectFormat::Toml).is_err());
+        assert!(parse_project("{", ProjectFormat::Json).is_err());
+    }
+
+    #[test]
+    pub fn rejects_projects_that_cannot_be_scheduled() {
+        let mut project = make_project();
+        assert_eq!(project.validate(), Ok(()));
+        project.tasks[1].planned_resources = 0.0;
+        assert_eq!(project.validate(), Err(ProjectError::PlannedResources(1)));
+
+        let mut project = make_project();
+        project.tasks[0].predecessors = vec![0];
+        assert_eq!(project.validate(), Err(ProjectError::OwnPredecessor(0)));
+        project.tasks[0].predecessors = vec![1];
+        project.tasks[1].predecessors = vec![0];
+        assert_eq!(project.validate(), Err(ProjectError::PredecessorCycle(0)));
+
+        let mut project = make_project();
+        project.allocations.push(Allocation {
+            taskid: 1,
+            resourceid: 0,
+            load: -0.5,
+        });
+        assert_eq!(
+            project.validate().unwrap_err().to_string(),
+            "The load of resource 0 on task 1 is negative"
+        );
+
+        // Instead of overflowing the stack when scheduling
+        let json = r#"{
+            "tasks": [
+                {"id": 0, "duration": 8, "label": "A", "earliest_start_date": "2023-06-01",
+                 "planned_resources": 1.0, "predecessors": [1]},
+                {"id": 1, "duration": 8, "label": "B", "earliest_start_date": "2023-06-01",
+                 "planned_resources": 1.0, "predecessors": [0]}
+            ],
+            "resources": [],
+            "allocations": []
+        }"#;
+        assert_eq!(
+            parse_project(json, ProjectFormat::Json)
+                .unwrap_err()
+                .to_string(),
+            "The predecessors of task 0 form a cycle"
+        );
+    }
 }
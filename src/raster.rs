@@ -0,0 +1,190 @@
+//! PNG and PDF output, rendered on the CPU from the same SVG documents the
+//! renderer produces. Text is drawn with an embedded font, so the output does
+//! not depend on the fonts installed on a machine.
+
+use std::fmt;
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref};
+use resvg::{
+    tiny_skia,
+    usvg::{self, fontdb, PostProcessingSteps, TreeParsing, TreePostProc},
+};
+use svg::Document;
+
+use crate::font::{FONT, FONT_FAMILY};
+
+/// The resolution of one SVG unit, as defined by CSS.
+pub const SVG_DPI: f32 = 96.0;
+
+#[derive(Debug)]
+pub enum RasterError {
+    Svg(usvg::Error),
+    EmptyDocument,
+    Png(String),
+}
+
+impl fmt::Display for RasterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RasterError::Svg(e) => write!(f, "Cannot read the chart: {}", e),
+            RasterError::EmptyDocument => write!(f, "The chart is empty"),
+            RasterError::Png(e) => write!(f, "Cannot encode PNG: {}", e),
+        }
+    }
+}
+
+fn font_database() -> fontdb::Database {
+    let mut db = fontdb::Database::new();
+    db.load_font_data(FONT.to_vec());
+    db.set_serif_family(FONT_FAMILY);
+    db.set_sans_serif_family(FONT_FAMILY);
+    db.set_monospace_family(FONT_FAMILY);
+    db
+}
+
+fn parse_tree(text: &str, db: &fontdb::Database) -> Result<usvg::Tree, RasterError> {
+    let options = usvg::Options {
+        font_family: FONT_FAMILY.to_string(),
+        ..usvg::Options::default()
+    };
+    let mut tree = usvg::Tree::from_str(text, &options).map_err(RasterError::Svg)?;
+    tree.postprocess(PostProcessingSteps::default(), db);
+    Ok(tree)
+}
+
+/// Parses the document into a render tree. Documents without a viewBox get
+/// one that covers their content, starting at the origin.
+fn to_tree(document: &Document) -> Result<usvg::Tree, RasterError> {
+    let db = font_database();
+    if document.get_attributes().contains_key("viewBox") {
+        return parse_tree(&document.to_string(), &db);
+    }
+
+    let tree = parse_tree(&document.to_string(), &db)?;
+    let bounds = tree
+        .root
+        .stroke_bounding_box
+        .ok_or(RasterError::EmptyDocument)?;
+    let (width, height) = (bounds.right().ceil(), bounds.bottom().ceil());
+    let sized = document
+        .clone()
+        .set("viewBox", (0, 0, width, height))
+        .set("width", width)
+        .set("height", height);
+    parse_tree(&sized.to_string(), &db)
+}
+
+/// Renders the document as PNG. A scale of 1.0 draws one pixel per SVG unit,
+/// i.e. 96 DPI.
+pub fn render_png(document: &Document, scale: f32) -> Result<Vec<u8>, RasterError> {
+    let tree = to_tree(document)?;
+    let width = (tree.size.width() * scale).ceil() as u32;
+    let height = (tree.size.height() * scale).ceil() as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or(RasterError::EmptyDocument)?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap
+        .encode_png()
+        .map_err(|e| RasterError::Png(e.to_string()))
+}
+
+/// Renders the documents into a PDF with one page per document, each page
+/// sized to fit its chart.
+pub fn render_pdf(documents: &[Document]) -> Result<Vec<u8>, RasterError> {
+    let mut pdf = Pdf::new();
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let mut next_id = Ref::new(3);
+    let mut page_ids = vec![];
+    let chart_name = Name(b"Chart");
+
+    for document in documents {
+        let tree = to_tree(document)?;
+        // svg2pdf maps one SVG unit to one point by default.
+        let (width, height) = (tree.size.width(), tree.size.height());
+
+        let page_id = next_id;
+        let content_id = Ref::new(page_id.get() + 1);
+        let chart_id = Ref::new(page_id.get() + 2);
+        next_id =
+            svg2pdf::convert_tree_into(&tree, svg2pdf::Options::default(), &mut pdf, chart_id);
+
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, width, height));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources().x_objects().pair(chart_name, chart_id);
+        page.finish();
+
+        let mut content = Content::new();
+        content
+            .transform([width, 0.0, 0.0, height, 0.0, 0.0])
+            .x_object(chart_name);
+        pdf.stream(content_id, &content.finish());
+
+        page_ids.push(page_id);
+    }
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .count(page_ids.len() as i32)
+        .kids(page_ids);
+    Ok(pdf.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use svg::{node::element::Text, Document};
+
+    use super::{render_pdf, render_png};
+
+    fn png_size(png: &[u8]) -> (u32, u32) {
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+        (width, height)
+    }
+
+    fn text_document() -> Document {
+        Document::new().add(
+            Text::new()
+                .set("x", 0)
+                .set("y", 20)
+                .set("font-size", 20)
+                .add(svg::node::Text::new("sgantt")),
+        )
+    }
+
+    #[test]
+    pub fn png_takes_size_from_view_box_and_scale() {
+        let document = Document::new().set("viewBox", (0, 0, 200, 100));
+        let png = render_png(&document, 2.0).unwrap();
+
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(png_size(&png), (400, 200));
+    }
+
+    #[test]
+    pub fn png_without_view_box_fits_content() {
+        let png = render_png(&text_document(), 1.0).unwrap();
+        let (width, height) = png_size(&png);
+
+        // The embedded font is used, so the text has the same size everywhere.
+        assert!(width > 40 && width < 100, "width {}", width);
+        assert!(height > 15 && height <= 25, "height {}", height);
+    }
+
+    #[test]
+    pub fn pdf_has_one_page_per_chart() {
+        let document = Document::new().set("viewBox", (0, 0, 200, 100));
+        let pdf = render_pdf(&[document, text_document()]).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("/MediaBox [0 0 200 100]"));
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use chrono::{prelude::*, Months};
 use svg::{
@@ -7,15 +7,226 @@ use svg::{
 };
 use time::Date;
 
-use crate::pom::Project;
+use crate::font::{self, FONT_FAMILIES};
+use crate::pom::{is_workday, load_percent, Project, Task, TaskDates};
+use crate::theme::Theme;
 
-const BAR_START_X: u32 = 150;
-const BAR_START_Y: u32 = 20;
-const BAR_HEIGHT: u32 = 8;
+/// Space between a label and the next column or bar.
+const LABEL_PADDING: f32 = 4.0;
+/// The widest label column sized to fit its labels, longer ones are shortened.
+const MAX_LABEL_COLUMN: f32 = 300.0;
+/// Space around the content of the canvas.
+const CANVAS_MARGIN: f32 = 2.0;
+
+/// The units of the calendar header, from the coarsest to the finest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeUnit {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Day,
+}
+
+impl TimeUnit {
+    pub fn from_name(name: &str) -> Option<TimeUnit> {
+        match name {
+            "year" | "years" => Some(TimeUnit::Year),
+            "quarter" | "quarters" => Some(TimeUnit::Quarter),
+            "month" | "months" => Some(TimeUnit::Month),
+            "week" | "weeks" => Some(TimeUnit::Week),
+            "day" | "days" => Some(TimeUnit::Day),
+            _ => None,
+        }
+    }
+
+    /// The typical length in days, used to pick the header tiers.
+    fn typical_days(self) -> f32 {
+        match self {
+            TimeUnit::Year => 365.0,
+            TimeUnit::Quarter => 91.0,
+            TimeUnit::Month => 30.0,
+            TimeUnit::Week => 7.0,
+            TimeUnit::Day => 1.0,
+        }
+    }
+
+    /// The narrowest segment still worth labelling.
+    fn min_width(self) -> f32 {
+        match self {
+            TimeUnit::Year => 25.0,
+            TimeUnit::Quarter => 40.0,
+            TimeUnit::Month => 20.0,
+            TimeUnit::Week => 20.0,
+            TimeUnit::Day => 12.0,
+        }
+    }
+
+    /// The unit shown above this one in an automatically chosen header.
+    fn parent(self) -> Option<TimeUnit> {
+        match self {
+            TimeUnit::Year => None,
+            TimeUnit::Quarter => Some(TimeUnit::Year),
+            TimeUnit::Month => Some(TimeUnit::Quarter),
+            TimeUnit::Week | TimeUnit::Day => Some(TimeUnit::Month),
+        }
+    }
+
+    fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            TimeUnit::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap(),
+            TimeUnit::Quarter => start_of_quarter(date),
+            TimeUnit::Month => date.with_day(1).unwrap(),
+            TimeUnit::Week => {
+                date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+            }
+            TimeUnit::Day => date,
+        }
+    }
+
+    fn next_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            TimeUnit::Year => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1).unwrap(),
+            TimeUnit::Quarter => next_quarter(date),
+            TimeUnit::Month => self
+                .start_of(date)
+                .checked_add_months(Months::new(1))
+                .unwrap(),
+            TimeUnit::Week => self.start_of(date) + chrono::Duration::days(7),
+            TimeUnit::Day => date + chrono::Duration::days(1),
+        }
+    }
+
+    /// The label of the segment containing the date. The year is left out
+    /// if a coarser tier shows it already.
+    fn label(self, date: NaiveDate, year_above: bool) -> String {
+        match self {
+            TimeUnit::Year => date.year().to_string(),
+            TimeUnit::Quarter if year_above => format!("Q{}", (date.month() - 1) / 3 + 1),
+            TimeUnit::Quarter => format!("Q{} {}", (date.month() - 1) / 3 + 1, date.year()),
+            TimeUnit::Month if year_above => date.format("%b").to_string(),
+            TimeUnit::Month => date.format("%b %Y").to_string(),
+            TimeUnit::Week => format!("W{}", date.iso_week().week()),
+            TimeUnit::Day => date.day().to_string(),
+        }
+    }
+}
+
+/// Picks the finest unit whose segments are wide enough for a label, with its
+/// parent above it.
+fn auto_header(day_width: f32) -> Vec<TimeUnit> {
+    let finest = [
+        TimeUnit::Day,
+        TimeUnit::Week,
+        TimeUnit::Month,
+        TimeUnit::Quarter,
+        TimeUnit::Year,
+    ]
+    .into_iter()
+    .find(|unit| unit.typical_days() * day_width >= unit.min_width())
+    .unwrap_or(TimeUnit::Year);
+    match finest.parent() {
+        Some(parent) => vec![parent, finest],
+        None => vec![finest],
+    }
+}
+
+pub struct RenderOptions {
+    /// SVG units per calendar day.
+    pub day_width: f32,
+    /// The first day shown, defaults to the reference date.
+    pub window_start: Option<Date>,
+    /// The last day shown. Defaults to the end of the horizon or, for the
+    /// Gantt chart, the end of the last task if that is later.
+    pub window_end: Option<Date>,
+    /// The days shown from the window start if no window end is given.
+    pub horizon_days: u32,
+    /// The header tiers from top to bottom, chosen from the day width if empty.
+    pub header: Vec<TimeUnit>,
+    /// The width of the label column left of the bars. Labels that do not fit
+    /// are shortened, with the full text as tooltip. Fits the longest label,
+    /// up to a limit, if not given.
+    pub label_width: Option<f32>,
+    /// The periods the load chart averages the load over: days, weeks, months,
+    /// quarters or years.
+    pub load_period: TimeUnit,
+    pub load_view: LoadView,
+    pub theme: Theme,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            day_width: 1.0,
+            window_start: None,
+            window_end: None,
+            horizon_days: 365,
+            header: vec![],
+            label_width: None,
+            load_period: TimeUnit::Day,
+            load_view: LoadView::Bands,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// Below this many units per day weekends are not shaded, the chart would
+/// turn into stripes.
+const MIN_DAY_WIDTH_FOR_WEEKENDS: f32 = 2.0;
+
+/// How the load chart shows the load of a resource.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadView {
+    /// A row coloured by load band.
+    Bands,
+    /// Bars of the load stacked by task, against a line at full capacity.
+    Histogram,
+}
+
+impl LoadView {
+    pub fn from_name(name: &str) -> Option<LoadView> {
+        match name {
+            "bands" => Some(LoadView::Bands),
+            "histogram" => Some(LoadView::Histogram),
+            _ => None,
+        }
+    }
+}
+
+/// Maps the days of the visible window to x positions.
+struct TimeScale {
+    start: Date,
+    /// The last day shown.
+    end: Date,
+    day_width: f32,
+    /// The x position of the window start, right of the label column.
+    left: f32,
+}
+
+impl TimeScale {
+    fn new(options: &RenderOptions, start_date: Date, min_end: Date, left: f32) -> TimeScale {
+        let start = options.window_start.unwrap_or(start_date);
+        let end = options.window_end.unwrap_or_else(|| {
+            (start + time::Duration::days(options.horizon_days as i64 - 1)).max(min_end)
+        });
+        TimeScale {
+            start,
+            end: end.max(start),
+            day_width: options.day_width,
+            left,
+        }
+    }
+
+    /// The x position of the start of the day, clamped to the window.
+    fn x(&self, date: Date) -> f32 {
+        let date = date.clamp(self.start, self.end + time::Duration::days(1));
+        self.left + (date - self.start).whole_days() as f32 * self.day_width
+    }
+}
 
 struct Point {
-    pub x: u32,
-    pub y: u32,
+    pub x: f32,
+    pub y: f32,
 }
 
 struct TaskPoints {
@@ -23,44 +234,93 @@ struct TaskPoints {
     pub end: Point,
 }
 
-fn add_v_line(doc: Document, x: u32) -> Document {
-    let data = Data::new().move_to((x, 0)).line_by((0, 5000)).close();
+/// The part of the chart covered by the grid: the header and the rows, from
+/// the left edge to the end of the window.
+struct ContentArea {
+    width: f32,
+    height: f32,
+}
+
+fn add_v_line(doc: Document, x: f32, area: &ContentArea, theme: &Theme) -> Document {
+    add_v_segment(doc, x, 0.0, area.height, &theme.grid)
+}
+
+fn add_v_segment(doc: Document, x: f32, y: f32, length: f32, color: &str) -> Document {
+    let data = Data::new().move_to((x, y)).line_by((0, length)).close();
 
     let path = Path::new()
         .set("fill", "none")
-        .set("stroke", "black")
+        .set("stroke", color)
         .set("stroke-width", "1")
         .set("d", data);
 
     doc.add(path)
 }
 
-fn add_h_line(doc: Document, y: u32) -> Document {
-    let data = Data::new().move_to((0, y)).line_by((5000, 0)).close();
+fn add_h_line(doc: Document, y: f32, area: &ContentArea, theme: &Theme) -> Document {
+    let data = Data::new().move_to((0, y)).line_by((area.width, 0)).close();
 
     let path = Path::new()
         .set("fill", "none")
-        .set("stroke", "black")
+        .set("stroke", theme.grid.as_str())
         .set("stroke-width", "1")
         .set("d", data);
 
     doc.add(path)
 }
 
-fn add_text_at(doc: Document, text: &str, x: u32, y: u32) -> Document {
+fn add_text_at(doc: Document, text: &str, x: f32, y: f32, theme: &Theme) -> Document {
+    doc.add(text_element(text, x, y, theme))
+}
+
+fn text_element(text: &str, x: f32, y: f32, theme: &Theme) -> element::Text {
     let txt = svg::node::Text::new(text);
     let mut text_elem = element::Text::new().add(txt);
     text_elem.assign("x", x);
     text_elem.assign("y", y); // weird magic to get the labels positioned correctly
-    text_elem.assign("font-size", 8);
-    text_elem.assign("fill", "black");
-    doc.add(text_elem)
+    text_elem.assign("font-size", theme.font_size);
+    text_elem.assign("fill", theme.text.as_str());
+    text_elem
+}
+
+/// Adds a label to the label column, starting at x and shortened to the
+/// column's width if need be. A shortened label shows the full text as tooltip.
+fn add_column_label(
+    doc: Document,
+    text: &str,
+    (x, y): (f32, f32),
+    column: f32,
+    theme: &Theme,
+) -> Document {
+    match font::ellipsize(text, theme.font_size, column - LABEL_PADDING - x) {
+        None => add_text_at(doc, text, x, y, theme),
+        Some(short) => doc.add(
+            text_element(&short, x, y, theme)
+                .add(element::Title::new().add(svg::node::Text::new(text))),
+        ),
+    }
+}
+
+/// The width of the label column: the one asked for, or else one fitting the
+/// widest label.
+fn label_column_width(label_widths: impl IntoIterator<Item = f32>, options: &RenderOptions) -> f32 {
+    options.label_width.unwrap_or_else(|| {
+        let widest = label_widths
+            .into_iter()
+            .fold(text_width("Resource", &options.theme), f32::max);
+        (widest + LABEL_PADDING).ceil().min(MAX_LABEL_COLUMN)
+    })
 }
 
 fn date_to_chrono_naive(date: &Date) -> NaiveDate {
     NaiveDate::from_ymd_opt(date.year(), date.month() as u32, date.day() as u32).unwrap()
 }
 
+fn chrono_naive_to_date(date: NaiveDate) -> Date {
+    let month = time::Month::try_from(date.month() as u8).unwrap();
+    Date::from_calendar_date(date.year(), month, date.day() as u8).unwrap()
+}
+
 fn start_of_quarter(date: NaiveDate) -> NaiveDate {
     let month = date.month();
     // !!! Since January is not Month 0 we need to subtract 1 to get the correct result here
@@ -74,210 +334,1365 @@ fn next_quarter(date: NaiveDate) -> NaiveDate {
     start_of_this_q.checked_add_months(Months::new(3)).unwrap()
 }
 
-fn render_gantt_layout(start_date: Date) -> Document {
-    let mut document = Document::new();
-    document = add_v_line(document, BAR_START_X);
-    document = add_text_at(document, "Task", 0, 8);
-
-    // add quarter lines:
-    let start_date_chrono = date_to_chrono_naive(&start_date);
-    let next_quarter_start = next_quarter(start_date_chrono);
-
-    // render lines for the next 4 quarters:
-    let mut quarter_start = next_quarter_start;
-    for _ in 0..=4 {
-        let days = (quarter_start - start_date_chrono).num_days();
-        document = add_v_line(document, BAR_START_X + days as u32);
-        let label_text = format!("{}", quarter_start);
-        document = add_text_at(document, &label_text, BAR_START_X + days as u32, 10);
-        quarter_start = quarter_start
-            .checked_add_months(chrono::Months::new(3))
-            .unwrap();
+/// Draws one tier of the header per unit. The boundaries of the coarsest tier
+/// run through the whole chart, those of the finest one as light lines if there
+/// are several tiers.
+fn render_header(
+    document: Document,
+    scale: &TimeScale,
+    tiers: &[TimeUnit],
+    area: &ContentArea,
+    theme: &Theme,
+) -> Document {
+    let mut document = document;
+    let tier_height = theme.tier_height();
+    let header_height = tiers.len() as f32 * tier_height;
+    let last_tier = tiers.len() - 1;
+    let window_end = date_to_chrono_naive(&scale.end);
+
+    for (level, unit) in tiers.iter().enumerate() {
+        let top = level as f32 * tier_height;
+        let year_above = tiers[..level].contains(&TimeUnit::Year)
+            || (*unit == TimeUnit::Month && tiers[..level].contains(&TimeUnit::Quarter));
+        if level > 0 {
+            let data = Data::new()
+                .move_to((scale.left, top))
+                .line_by((area.width - scale.left, 0))
+                .close();
+            document = document.add(
+                Path::new()
+                    .set("fill", "none")
+                    .set("stroke", theme.grid.as_str())
+                    .set("stroke-width", "1")
+                    .set("d", data),
+            );
+        }
+
+        let mut segment_start = date_to_chrono_naive(&scale.start);
+        while segment_start <= window_end {
+            let next_start = unit.next_start(segment_start);
+            let x = scale.x(chrono_naive_to_date(segment_start));
+            let width = scale.x(chrono_naive_to_date(next_start)) - x;
+
+            if unit.start_of(segment_start) == segment_start {
+                document = if level == 0 {
+                    add_v_line(document, x, area, theme)
+                } else if level == last_tier {
+                    let line = add_v_segment(document, x, top, tier_height, &theme.grid);
+                    add_v_segment(
+                        line,
+                        x,
+                        header_height,
+                        area.height - header_height,
+                        &theme.minor_grid,
+                    )
+                } else {
+                    add_v_segment(document, x, top, tier_height, &theme.grid)
+                };
+            }
+
+            let label = unit.label(segment_start, year_above);
+            if text_width(&label, theme) + 1.0 <= width {
+                document = add_text_at(document, &label, x + 1.0, top + theme.font_size, theme);
+            }
+            segment_start = next_start;
+        }
+    }
+    document
+}
+
+/// Draws the label column with its title and the calendar header for the
+/// given number of rows, returning the y position of the first row and the
+/// area of the grid.
+fn render_gantt_layout(
+    scale: &TimeScale,
+    options: &RenderOptions,
+    column_title: &str,
+    rows: usize,
+    holidays: &[Date],
+) -> (Document, f32, ContentArea) {
+    let tiers = if options.header.is_empty() {
+        auto_header(scale.day_width)
+    } else {
+        options.header.clone()
+    };
+    let theme = &options.theme;
+    let header_height = tiers.len() as f32 * theme.tier_height();
+    let bar_start_y = header_height + 2.0;
+    let area = ContentArea {
+        width: scale.x(scale.end + time::Duration::days(1)),
+        height: bar_start_y + theme.row_height() * rows as f32 - theme.row_spacing / 2.0,
+    };
+
+    let mut document = Document::new().add(
+        element::Rectangle::new()
+            .set("width", "100%")
+            .set("height", "100%")
+            .set("fill", theme.background.as_str()),
+    );
+    document = render_non_working_days(document, scale, &area, header_height, holidays, options);
+    document = add_v_line(document, scale.left, &area, theme);
+    document = add_text_at(document, column_title, 0.0, header_height - 2.0, theme);
+    document = render_header(document, scale, &tiers, &area, theme);
+    document = add_h_line(document, area.height, &area, theme);
+    document = document
+        .set("style", format!("background-color:{}", theme.background))
+        .set("font-family", FONT_FAMILIES);
+    (document, bar_start_y, area)
+}
+
+fn add_background(
+    document: Document,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    color: &str,
+) -> Document {
+    document.add(
+        element::Rectangle::new()
+            .set("x", x)
+            .set("y", y)
+            .set("width", width)
+            .set("height", height)
+            .set("fill", color),
+    )
+}
+
+/// Shades weekends and holidays below the header. Consecutive weekend days
+/// share one rectangle.
+fn render_non_working_days(
+    document: Document,
+    scale: &TimeScale,
+    area: &ContentArea,
+    top: f32,
+    holidays: &[Date],
+    options: &RenderOptions,
+) -> Document {
+    let mut document = document;
+    let height = area.height - top;
+
+    if scale.day_width >= MIN_DAY_WIDTH_FOR_WEEKENDS {
+        let mut day = scale.start;
+        while day <= scale.end {
+            if is_weekend(day) {
+                let first = day;
+                while day <= scale.end && is_weekend(day) {
+                    day = day.next_day().unwrap();
+                }
+                let x = scale.x(first);
+                document = add_background(
+                    document,
+                    x,
+                    top,
+                    scale.x(day) - x,
+                    height,
+                    &options.theme.weekend,
+                );
+            } else {
+                day = day.next_day().unwrap();
+            }
+        }
+    }
+
+    for holiday in holidays
+        .iter()
+        .filter(|x| (scale.start..=scale.end).contains(*x))
+    {
+        document = add_background(
+            document,
+            scale.x(*holiday),
+            top,
+            scale.day_width,
+            height,
+            &options.theme.holiday,
+        );
+    }
+    document
+}
+
+fn is_weekend(date: Date) -> bool {
+    matches!(
+        date.weekday(),
+        time::Weekday::Saturday | time::Weekday::Sunday
+    )
+}
+
+/// Draws the line of the status date through the rows and labels it below the
+/// grid, returning the bottom of the label. Nothing is drawn if the date is
+/// outside the window.
+fn render_status_line(
+    document: Document,
+    scale: &TimeScale,
+    status_date: Date,
+    area: &ContentArea,
+    options: &RenderOptions,
+) -> (Document, f32) {
+    if status_date < scale.start || status_date > scale.end {
+        return (document, area.height);
     }
-    document = document.set("style", "background-color:white");
+    let theme = &options.theme;
+    let x = scale.x(status_date);
+    let data = Data::new()
+        .move_to((x, 0))
+        .line_to((x, area.height + 2.0))
+        .close();
+    let line = Path::new()
+        .set("class", "status-date")
+        .set("fill", "none")
+        .set("stroke", theme.status_line.as_str())
+        .set("stroke-width", "1")
+        .set("stroke-dasharray", "3 2")
+        .set("d", data);
+
+    let label = element::Text::new()
+        .add(svg::node::Text::new(format!("Status {}", status_date)))
+        .set("x", x)
+        .set("y", area.height + theme.tier_height())
+        .set("font-size", theme.font_size)
+        .set("text-anchor", "middle")
+        .set("fill", theme.status_line.as_str());
+    (
+        document.add(line).add(label),
+        area.height + theme.tier_height(),
+    )
+}
+
+/// The width of a text in the theme's font size.
+fn text_width(text: &str, theme: &Theme) -> f32 {
+    font::text_width(text, theme.font_size)
+}
+
+/// Sizes the document to the grid and anything sticking out of it on the right.
+fn set_canvas_size(document: Document, right_edge: f32, bottom_edge: f32) -> Document {
+    let width = (right_edge + CANVAS_MARGIN).ceil();
+    let height = (bottom_edge + CANVAS_MARGIN).ceil();
     document
+        .set("viewBox", (0, 0, width, height))
+        .set("width", width)
+        .set("height", height)
 }
 
-fn date_to_x_pos(start_date: Date, rel_date: Date) -> u32 {
-    if rel_date > start_date {
-        return BAR_START_X + (rel_date - start_date).whole_days() as u32;
+/// The text left of a task's bar: its name, remaining working days and planned FTE.
+pub(crate) fn task_label(p: &Project, task: &Task, start_date: Date) -> String {
+    let working_days = task.get_actual_remaining_work_days(p, start_date);
+    format!(
+        "{}, {} days, {} FTE",
+        task.label, working_days, task.planned_resources
+    )
+}
+
+/// The text right of a task's bar: the allocated resources and the allocated
+/// versus planned FTE.
+pub(crate) fn allocation_label(p: &Project, task: &Task) -> String {
+    let mut label = String::from("");
+    let mut sum = 0.0;
+    for alloc in p.allocations.iter().filter(|x| x.taskid == task.id) {
+        let res = p.get_resource_by_id(alloc.resourceid);
+        label.push_str(&format!("{}:{}, ", res.label, load_percent(alloc.load)));
+        sum += alloc.load;
     }
-    BAR_START_X
+
+    label.push_str(&format!("FTE:{}/{}", sum, task.planned_resources));
+    label
 }
 
-pub fn render_gantt(p: &Project, start_date: Date) -> Document {
-    let mut document = render_gantt_layout(start_date);
-    let mut task_number = 0;
+/// The last day of the last task and the tasks with a bar in the window.
+fn visible_tasks<'a>(
+    p: &'a Project,
+    start_date: Date,
+    options: &RenderOptions,
+) -> (Date, Vec<&'a Task>) {
+    let last_end = p
+        .tasks
+        .iter()
+        .map(|x| x.get_actual_end_date(p) - time::Duration::days(1))
+        .max()
+        .unwrap_or(start_date);
+    let scale = TimeScale::new(options, start_date, last_end, 0.0);
+    let visible = p
+        .tasks
+        .iter()
+        .filter(|x| bar_extent(p, x, &scale).1 > 0.0)
+        .collect();
+    (last_end, visible)
+}
+
+pub fn render_gantt(p: &Project, start_date: Date, options: &RenderOptions) -> Document {
+    let (last_end, visible_tasks) = visible_tasks(p, start_date, options);
+    let labels: Vec<String> = visible_tasks
+        .iter()
+        .map(|x| task_label(p, x, start_date))
+        .collect();
+    let column = label_column_width(
+        labels.iter().map(|x| text_width(x, &options.theme)),
+        options,
+    );
+    let scale = TimeScale::new(options, start_date, last_end, column);
+    let theme = &options.theme;
+    let (mut document, bar_start_y, area) =
+        render_gantt_layout(&scale, options, "Task", visible_tasks.len(), &p.holidays);
+    let mut used_tag_rules = BTreeSet::new();
 
     // We use this map to store the "end positions" for all tasks.
     // These are then used in a second pass to draw dependency arrows
     let mut task_start_and_end_points = HashMap::<u32, TaskPoints>::new();
 
-    for task in p.tasks.iter() {
-        let task_start_date = task.get_actual_start_date(p);
-        let task_end_date = task.get_actual_end_date(p);
-        let days = task.get_actual_remaining_calender_days(p, start_date) as i32;
-        let working_days = task.get_actual_remaining_work_days(p, start_date) as i32;
-
-        if days > 0 {
-            let element_y = BAR_START_Y + BAR_HEIGHT * task_number + 2 * task_number;
-            let element_x = date_to_x_pos(start_date, task_start_date);
-
-            println!(
-                "Rendering {}, start date {} end date {}",
-                task.id, task_start_date, task_end_date
-            );
+    for (task_number, (task, label)) in visible_tasks.iter().zip(labels.iter()).enumerate() {
+        let element_y = bar_start_y + theme.row_height() * task_number as f32;
+        let (points, tag_rule);
+        (document, points, tag_rule) =
+            add_task_bar(document, p, task, element_y, &scale, start_date, theme);
+        used_tag_rules.extend(tag_rule);
+        document = add_column_label(
+            document,
+            label,
+            (0.0, theme.label_y(element_y)),
+            column,
+            theme,
+        );
+        document = add_h_line(document, element_y - theme.row_spacing / 2.0, &area, theme);
+        task_start_and_end_points.insert(task.id, points);
+    }
 
-            let data = Data::new()
-                .move_to((element_x, element_y))
-                .line_by((days, 0))
-                .line_by((0, BAR_HEIGHT))
-                .line_by((-days, 0))
-                .close();
+    let (document, routes) =
+        render_dependency_arrows(p, &task_start_and_end_points, document, options);
+    let (document, right_edge) =
+        render_resources(p, &task_start_and_end_points, document, &routes, theme);
+    let right_edge = right_edge.max(area.width);
 
-            let path = Path::new()
-                .set("fill", "#A0A0CC")
-                .set("stroke", "#7979CC")
-                .set("stroke-width", "1")
-                .set("d", data);
+    let (document, bottom_edge) = render_status_line(document, &scale, start_date, &area, options);
+    let (document, bottom_edge) =
+        render_tag_legend(document, &used_tag_rules, bottom_edge, right_edge, theme);
+    set_canvas_size(document, right_edge, bottom_edge)
+}
 
-            let task_label = format!(
-                "{}, {} days, {} FTE",
-                task.label, working_days, task.planned_resources
+/// The groups of the swimlane chart: a resource's tasks, or those nobody is
+/// allocated to.
+struct Lane<'a> {
+    label: String,
+    resource: Option<u32>,
+    tasks: Vec<&'a Task>,
+}
+
+/// The indentation of task labels below the label of their lane.
+const LANE_INDENT: f32 = 8.0;
+
+/// Draws a Gantt chart grouped into one lane per resource with the tasks it
+/// is allocated to, below a row with its name. The resource's daily load
+/// tints the background of the lane. Tasks nobody is allocated to get a lane
+/// of their own. Dependency links are left out, tasks may show up in several
+/// lanes.
+pub fn render_swimlanes(p: &Project, start_date: Date, options: &RenderOptions) -> Document {
+    let theme = &options.theme;
+    let (last_end, visible_tasks) = visible_tasks(p, start_date, options);
+    let allocated = |task: &Task, resource_id: u32| {
+        p.allocations
+            .iter()
+            .any(|x| x.taskid == task.id && x.resourceid == resource_id)
+    };
+    let mut lanes: Vec<Lane> = p
+        .resources
+        .iter()
+        .map(|res| Lane {
+            label: res.label.clone(),
+            resource: Some(res.id),
+            tasks: visible_tasks
+                .iter()
+                .filter(|task| allocated(task, res.id))
+                .copied()
+                .collect(),
+        })
+        .collect();
+    let unassigned: Vec<&Task> = visible_tasks
+        .iter()
+        .filter(|task| !p.allocations.iter().any(|x| x.taskid == task.id))
+        .copied()
+        .collect();
+    if !unassigned.is_empty() {
+        lanes.push(Lane {
+            label: "Unassigned".to_string(),
+            resource: None,
+            tasks: unassigned,
+        });
+    }
+
+    let task_labels: HashMap<u32, String> = visible_tasks
+        .iter()
+        .map(|task| (task.id, task_label(p, task, start_date)))
+        .collect();
+    let column = label_column_width(
+        lanes.iter().map(|x| text_width(&x.label, theme)).chain(
+            task_labels
+                .values()
+                .map(|x| LANE_INDENT + text_width(x, theme)),
+        ),
+        options,
+    );
+    let scale = TimeScale::new(options, start_date, last_end, column);
+    let rows = lanes.iter().map(|x| x.tasks.len() + 1).sum();
+    let (mut document, bar_start_y, area) =
+        render_gantt_layout(&scale, options, "Resource", rows, &p.holidays);
+    let mut used_tag_rules = BTreeSet::new();
+    let mut right_edge = area.width;
+
+    let dates = p.task_dates();
+    let mut row = 0;
+    for lane in lanes.iter() {
+        let lane_top = bar_start_y + theme.row_height() * row as f32 - theme.row_spacing / 2.0;
+        if let Some(resource_id) = lane.resource {
+            let height = theme.row_height() * (lane.tasks.len() + 1) as f32;
+            for run in load_runs(p, &dates, resource_id, &scale, TimeUnit::Day) {
+                let load = run.total();
+                if load == 0.0 {
+                    continue;
+                }
+                let x = scale.x(run.first);
+                document = document.add(
+                    element::Rectangle::new()
+                        .set("class", "lane-load")
+                        .set("x", x)
+                        .set("y", lane_top)
+                        .set("width", scale.x(run.after_last) - x)
+                        .set("height", height)
+                        .set("fill", theme.load_color(load))
+                        .set("fill-opacity", 0.3),
+                );
+            }
+        }
+        document = add_h_line(document, lane_top, &area, theme);
+        document = add_column_label(
+            document,
+            &lane.label,
+            (0.0, theme.label_y(lane_top + theme.row_spacing / 2.0)),
+            column,
+            theme,
+        );
+        row += 1;
+
+        for task in lane.tasks.iter() {
+            let element_y = bar_start_y + theme.row_height() * row as f32;
+            let (points, tag_rule);
+            (document, points, tag_rule) =
+                add_task_bar(document, p, task, element_y, &scale, start_date, theme);
+            used_tag_rules.extend(tag_rule);
+            document = add_column_label(
+                document,
+                &task_labels[&task.id],
+                (LANE_INDENT, theme.label_y(element_y)),
+                column,
+                theme,
             );
-            document = document.add(path);
-            document = add_text_at(document, &task_label, 0, element_y + 6);
-            document = add_h_line(document, element_y - 1);
-
-            task_start_and_end_points.insert(
-                task.id,
-                TaskPoints {
-                    start: Point {
-                        x: element_x,
-                        y: element_y,
-                    },
-                    end: Point {
-                        x: element_x + days as u32,
-                        y: element_y,
-                    },
-                },
+
+            // The share of the lane's resource, or the planned FTE if nobody
+            // is allocated.
+            let label = match lane.resource {
+                Some(resource_id) => {
+                    let load: f32 = p
+                        .allocations
+                        .iter()
+                        .filter(|x| x.taskid == task.id && x.resourceid == resource_id)
+                        .map(|x| x.load)
+                        .sum();
+                    load_percent(load)
+                }
+                None => allocation_label(p, task),
+            };
+            let x = points.end.x + LABEL_PADDING / 2.0;
+            document = add_text_at(document, &label, x, theme.label_y(element_y), theme);
+            right_edge = right_edge.max(x + text_width(&label, theme));
+            row += 1;
+        }
+    }
+
+    let (document, bottom_edge) = render_status_line(document, &scale, start_date, &area, options);
+    let (document, bottom_edge) =
+        render_tag_legend(document, &used_tag_rules, bottom_edge, right_edge, theme);
+    set_canvas_size(document, right_edge, bottom_edge)
+}
+
+/// Consecutive periods of the window in which the tasks put the same load on
+/// a resource.
+struct LoadRun {
+    first: Date,
+    /// The day after the last of the run.
+    after_last: Date,
+    /// The tasks' ids and their load in each period, relative to the
+    /// resource's capacity.
+    task_loads: Vec<(u32, f32)>,
+}
+
+impl LoadRun {
+    fn total(&self) -> f32 {
+        self.task_loads.iter().map(|x| x.1).sum()
+    }
+}
+
+/// Splits the window into periods of the unit, the first and last clipped to
+/// the window, as first day and day after the last.
+fn load_periods(scale: &TimeScale, unit: TimeUnit) -> Vec<(Date, Date)> {
+    let window_end = scale.end + time::Duration::days(1);
+    let mut periods = vec![];
+    let mut first = scale.start;
+    while first < window_end {
+        let next = chrono_naive_to_date(unit.next_start(date_to_chrono_naive(&first)));
+        let after_last = next.min(window_end);
+        periods.push((first, after_last));
+        first = after_last;
+    }
+    periods
+}
+
+/// The load of each task on the resource over the period: the hours the
+/// task needs from it relative to the hours it works in the period. Tasks
+/// pause on holidays, so only work for a resource without any output is an
+/// infinite load.
+fn period_task_loads(
+    p: &Project,
+    dates: &TaskDates,
+    resource_id: u32,
+    (first, after_last): (Date, Date),
+) -> Vec<(u32, f32)> {
+    let resource = p.get_resource_by_id(resource_id);
+    let mut capacity = 0.0;
+    let mut hours: Vec<(u32, f32)> = vec![];
+    let mut day = first;
+    while day < after_last {
+        capacity += resource.capacity_on(day, &p.holidays);
+        for (task_id, demand) in p.calculate_task_demands(dates, resource_id, day) {
+            match hours.iter_mut().find(|x| x.0 == task_id) {
+                Some(task_hours) => task_hours.1 += demand,
+                None => hours.push((task_id, demand)),
+            }
+        }
+        day += time::Duration::days(1);
+    }
+    hours
+        .into_iter()
+        .map(|(task_id, demand)| match capacity > 0.0 {
+            true => (task_id, demand / capacity),
+            false => (task_id, f32::INFINITY),
+        })
+        .collect()
+}
+
+/// The load of the resource per period of the unit, consecutive periods with
+/// the same load merged.
+fn load_runs(
+    p: &Project,
+    dates: &TaskDates,
+    resource_id: u32,
+    scale: &TimeScale,
+    unit: TimeUnit,
+) -> Vec<LoadRun> {
+    let mut runs: Vec<LoadRun> = vec![];
+    for period in load_periods(scale, unit) {
+        let task_loads = period_task_loads(p, dates, resource_id, period);
+        match runs.last_mut() {
+            Some(run) if run.task_loads == task_loads => run.after_last = period.1,
+            _ => runs.push(LoadRun {
+                first: period.0,
+                after_last: period.1,
+                task_loads,
+            }),
+        }
+    }
+    runs
+}
+
+/// Lists the tag colour rules used by the bars in a legend.
+fn render_tag_legend(
+    document: Document,
+    used_tag_rules: &BTreeSet<usize>,
+    top: f32,
+    width: f32,
+    theme: &Theme,
+) -> (Document, f32) {
+    let entries: Vec<(&str, &str)> = used_tag_rules
+        .iter()
+        .map(|rule| {
+            let rule = &theme.tag_colors[*rule];
+            (rule.tag.as_str(), rule.color.as_str())
+        })
+        .collect();
+    render_legend(document, &entries, top, width, theme)
+}
+
+/// Lists the labels with a swatch of their colour below the chart, wrapping
+/// into several lines if they do not fit the width. Returns the bottom of the
+/// legend, nothing is drawn without entries.
+fn render_legend(
+    document: Document,
+    entries: &[(&str, &str)],
+    top: f32,
+    width: f32,
+    theme: &Theme,
+) -> (Document, f32) {
+    if entries.is_empty() {
+        return (document, top);
+    }
+    let swatch = theme.font_size;
+    let line_height = theme.font_size + 4.0;
+    let mut legend = element::Group::new().set("class", "legend");
+    let (mut x, mut y) = (0.0, top + 4.0);
+    for (label, color) in entries {
+        let entry_width = swatch + 3.0 + text_width(label, theme);
+        if x > 0.0 && x + entry_width > width {
+            x = 0.0;
+            y += line_height;
+        }
+        legend = legend
+            .add(
+                element::Rectangle::new()
+                    .set("x", x)
+                    .set("y", y)
+                    .set("width", swatch)
+                    .set("height", swatch)
+                    .set("fill", *color)
+                    .set("stroke", theme.grid.as_str())
+                    .set("stroke-width", "0.5"),
+            )
+            .add(
+                element::Text::new()
+                    .add(svg::node::Text::new(*label))
+                    .set("x", x + swatch + 3.0)
+                    .set("y", y + swatch - 1.0)
+                    .set("font-size", theme.font_size)
+                    .set("fill", theme.text.as_str()),
             );
+        x += entry_width + 10.0;
+    }
+    (document.add(legend), y + line_height)
+}
+
+/// Draws the bar of a task in the row starting at y, greying out the work
+/// before the reference date and marking the edges cut off by the window.
+/// Returns where the bar starts and ends and the tag colour rule used, if any.
+fn add_task_bar(
+    document: Document,
+    p: &Project,
+    task: &Task,
+    element_y: f32,
+    scale: &TimeScale,
+    start_date: Date,
+    theme: &Theme,
+) -> (Document, TaskPoints, Option<usize>) {
+    let mut document = document;
+    let task_start_date = task.get_actual_start_date(p);
+    let task_end_date = task.get_actual_end_date(p);
+    let (element_x, width) = bar_extent(p, task, scale);
 
-            task_number += 1;
+    // Work before the reference date is history and drawn greyed out.
+    let history_end = scale.x(task_end_date.min(start_date)).max(element_x);
+    document = add_bar(
+        document,
+        element_x,
+        element_y,
+        history_end - element_x,
+        (&theme.history_fill, &theme.history_stroke),
+        theme,
+    );
+    let tag_rule = task
+        .color
+        .is_none()
+        .then(|| theme.tag_rule(&task.tags))
+        .flatten();
+    let colors = match (&task.color, tag_rule) {
+        (Some(color), _) => (color.as_str(), color.as_str()),
+        (None, Some(rule)) => {
+            let color = theme.tag_colors[rule].color.as_str();
+            (color, color)
         }
+        (None, None) => (theme.bar_fill.as_str(), theme.bar_stroke.as_str()),
+    };
+    document = add_bar(
+        document,
+        history_end,
+        element_y,
+        element_x + width - history_end,
+        colors,
+        theme,
+    );
+    if task_start_date < scale.start {
+        document = add_continuation_marker(document, element_x, element_y, -1.0, theme);
+    }
+    if task_end_date > scale.end + time::Duration::days(1) {
+        document = add_continuation_marker(document, element_x + width, element_y, 1.0, theme);
     }
 
-    document = render_dependency_arrows(p, &task_start_and_end_points, document);
-    document = render_resources(p, &task_start_and_end_points, document);
+    let points = TaskPoints {
+        start: Point {
+            x: element_x,
+            y: element_y,
+        },
+        end: Point {
+            x: element_x + width,
+            y: element_y,
+        },
+    };
+    (document, points, tag_rule)
+}
 
-    document
+/// Draws a bar with the given fill and stroke colours, nothing if it is empty.
+fn add_bar(
+    document: Document,
+    x: f32,
+    y: f32,
+    width: f32,
+    colors: (&str, &str),
+    theme: &Theme,
+) -> Document {
+    if width <= 0.0 {
+        return document;
+    }
+    let data = Data::new()
+        .move_to((x, y))
+        .line_by((width, 0))
+        .line_by((0, theme.bar_height))
+        .line_by((-width, 0))
+        .close();
+
+    let path = Path::new()
+        .set("fill", colors.0)
+        .set("stroke", colors.1)
+        .set("stroke-width", "1")
+        .set("d", data);
+    document.add(path)
+}
+
+/// Marks a bar cut off by the window with a small arrow head at its edge,
+/// pointing left (direction -1) or right (1).
+fn add_continuation_marker(
+    document: Document,
+    x: f32,
+    y: f32,
+    direction: f32,
+    theme: &Theme,
+) -> Document {
+    let data = Data::new()
+        .move_to((x + direction * 4.0, y + theme.bar_height / 2.0))
+        .line_to((x, y))
+        .line_to((x, y + theme.bar_height))
+        .close();
+
+    let path = Path::new()
+        .set("class", "continuation")
+        .set("fill", theme.continuation.as_str())
+        .set("stroke", "none")
+        .set("d", data);
+    document.add(path)
+}
+
+/// The x position and width of the task's bar, clipped to the window.
+fn bar_extent(p: &Project, task: &Task, scale: &TimeScale) -> (f32, f32) {
+    let x = scale.x(task.get_actual_start_date(p));
+    (x, scale.x(task.get_actual_end_date(p)) - x)
 }
 
+/// Labels the bars with their allocations, right of the bar and of any link
+/// crossing the row there. Returns the right edge of the labels.
 fn render_resources(
     p: &Project,
     task_start_and_end_points: &HashMap<u32, TaskPoints>,
     document: element::SVG,
-) -> element::SVG {
+    routes: &[Vec<(f32, f32)>],
+    theme: &Theme,
+) -> (element::SVG, f32) {
     let mut the_doc = document;
+    let mut right_edge: f32 = 0.0;
     for task in p.tasks.iter() {
-        let own_points = task_start_and_end_points.get(&task.id).unwrap();
-
-        // find own allocations and create label for them:
-        let mut label = String::from("");
-        let mut sum = 0.0;
-        for alloc in p.allocations.iter().filter(|x| x.taskid == task.id) {
-            let res = p.get_resource_by_id(alloc.resourceid);
-            label.push_str(&format!("{}:{}%, ", res.label, alloc.load * 100.0));
-            sum += alloc.load;
+        // Tasks outside the window have no bar to label.
+        let Some(own_points) = task_start_and_end_points.get(&task.id) else {
+            continue;
+        };
+
+        let label = allocation_label(p, task);
+        let width = text_width(&label, theme);
+        let x = free_position(
+            own_points.end.x + LABEL_PADDING / 2.0,
+            width,
+            &link_crossings(routes, own_points.end.y, theme),
+        );
+        the_doc = add_text_at(the_doc, &label, x, theme.label_y(own_points.end.y), theme);
+        right_edge = right_edge.max(x + width);
+    }
+    (the_doc, right_edge)
+}
+
+/// The first position from x on where something of the given width overlaps
+/// none of the ranges.
+fn free_position(x: f32, width: f32, taken: &[(f32, f32)]) -> f32 {
+    let mut x = x;
+    while let Some((_, end)) = taken
+        .iter()
+        .find(|(start, end)| *start < x + width && *end > x)
+    {
+        x = *end;
+    }
+    x
+}
+
+/// The x ranges in which the links cross the row starting at y.
+fn link_crossings(routes: &[Vec<(f32, f32)>], y: f32, theme: &Theme) -> Vec<(f32, f32)> {
+    let (top, bottom) = (y, y + theme.bar_height);
+    routes
+        .iter()
+        .flat_map(|route| route.windows(2))
+        .filter(|segment| {
+            let (y1, y2) = (segment[0].1, segment[1].1);
+            y1.min(y2) <= bottom && y1.max(y2) >= top
+        })
+        .map(|segment| {
+            let (x1, x2) = (segment[0].0, segment[1].0);
+            (x1.min(x2) - 1.0, x1.max(x2) + 1.0)
+        })
+        .collect()
+}
+
+/// How a dependency link is drawn.
+#[derive(Clone, Copy, PartialEq)]
+enum LinkKind {
+    /// The predecessor's end determines the successor's start.
+    Driving,
+    /// The successor would not start earlier without the predecessor.
+    Slack,
+    /// A driving link on the critical path.
+    Critical,
+}
+
+impl LinkKind {
+    fn name(self) -> &'static str {
+        match self {
+            LinkKind::Driving => "driving",
+            LinkKind::Slack => "slack",
+            LinkKind::Critical => "critical",
         }
+    }
+
+    fn color(self, options: &RenderOptions) -> &str {
+        match self {
+            LinkKind::Driving | LinkKind::Slack => &options.theme.link,
+            LinkKind::Critical => &options.theme.critical_link,
+        }
+    }
+}
+
+const LINK_KINDS: [LinkKind; 3] = [LinkKind::Driving, LinkKind::Slack, LinkKind::Critical];
 
-        label.push_str(&format!("FTE:{}/{}", sum, task.planned_resources));
+/// The distance links keep from bars before turning.
+const LINK_GAP: f32 = 4.0;
 
-        the_doc = add_text_at(the_doc, &label, own_points.end.x, own_points.end.y + 6);
+/// Arrow heads for the links, one per kind since markers cannot take the
+/// colour of the line they end.
+fn link_markers(options: &RenderOptions) -> element::Definitions {
+    let mut defs = element::Definitions::new();
+    for kind in LINK_KINDS {
+        let head = Path::new()
+            .set("d", "M0,0 L6,3 L0,6 z")
+            .set("fill", kind.color(options));
+        defs = defs.add(
+            element::Marker::new()
+                .set("id", format!("arrow-{}", kind.name()))
+                .set("viewBox", (0, 0, 6, 6))
+                .set("refX", 6)
+                .set("refY", 3)
+                .set("markerWidth", 4)
+                .set("markerHeight", 4)
+                .set("orient", "auto")
+                .add(head),
+        );
     }
-    the_doc
+    defs
+}
+
+/// Routes a link from the end of the predecessor's bar to the start of the
+/// successor's with horizontal and vertical segments. The vertical segment
+/// runs in the first channel right of the predecessor that no bar of the rows
+/// in between covers. If that channel is right of the successor's start, the
+/// link turns back in the gap next to the successor's row. Returns the corners
+/// of the link.
+fn route_link(
+    from: &TaskPoints,
+    to: &TaskPoints,
+    task_start_and_end_points: &HashMap<u32, TaskPoints>,
+    theme: &Theme,
+) -> Vec<(f32, f32)> {
+    let (x1, y1) = (from.end.x, from.end.y + theme.bar_height / 2.0);
+    let (x2, y2) = (to.start.x, to.start.y + theme.bar_height / 2.0);
+    let (upper, lower) = if from.end.y < to.start.y {
+        (from.end.y, to.start.y)
+    } else {
+        (to.start.y, from.end.y)
+    };
+    let rows_between: Vec<&TaskPoints> = task_start_and_end_points
+        .values()
+        .filter(|x| x.start.y > upper && x.start.y < lower)
+        .collect();
+
+    let mut channel = x1 + LINK_GAP;
+    while let Some(bar) = rows_between
+        .iter()
+        .find(|bar| bar.start.x - 1.0 <= channel && channel <= bar.end.x + 1.0)
+    {
+        channel = bar.end.x + LINK_GAP;
+    }
+
+    if channel <= x2 - LINK_GAP {
+        vec![(x1, y1), (channel, y1), (channel, y2), (x2, y2)]
+    } else {
+        let gap_y = if from.end.y < to.start.y {
+            to.start.y - theme.row_spacing / 2.0
+        } else {
+            to.start.y + theme.bar_height + theme.row_spacing / 2.0
+        };
+        vec![
+            (x1, y1),
+            (channel, y1),
+            (channel, gap_y),
+            (x2 - LINK_GAP, gap_y),
+            (x2 - LINK_GAP, y2),
+            (x2, y2),
+        ]
+    }
+}
+
+/// The path through the corners of a link, whose segments are all horizontal
+/// or vertical.
+fn link_path(corners: &[(f32, f32)]) -> Data {
+    let mut data = Data::new().move_to(corners[0]);
+    for pair in corners.windows(2) {
+        data = if pair[0].0 == pair[1].0 {
+            data.vertical_line_to(pair[1].1)
+        } else {
+            data.horizontal_line_to(pair[1].0)
+        };
+    }
+    data
 }
 
 fn render_dependency_arrows(
     p: &Project,
     task_start_and_end_points: &HashMap<u32, TaskPoints>,
     document: Document,
-) -> Document {
-    let mut the_doc = document;
+    options: &RenderOptions,
+) -> (Document, Vec<Vec<(f32, f32)>>) {
+    let critical_path = p.critical_path();
+    let mut the_doc = document.add(link_markers(options));
+    let mut routes = vec![];
     // second pass, draw dependency arrows
     for task in p.tasks.iter() {
-        let own_points = task_start_and_end_points.get(&task.id).unwrap();
+        let Some(own_points) = task_start_and_end_points.get(&task.id) else {
+            continue;
+        };
         for pred_id in task.predecessors.iter() {
-            let pred_points = task_start_and_end_points.get(pred_id).unwrap();
-
-            let data = Data::new()
-                .move_to((pred_points.end.x, pred_points.end.y + 4))
-                .line_to((own_points.start.x, own_points.start.y + 4))
-                .close();
+            let Some(pred_points) = task_start_and_end_points.get(pred_id) else {
+                continue;
+            };
+            let kind = if !task.is_driven_by(p, *pred_id) {
+                LinkKind::Slack
+            } else if critical_path.contains(&task.id) && critical_path.contains(pred_id) {
+                LinkKind::Critical
+            } else {
+                LinkKind::Driving
+            };
 
-            let path = Path::new()
+            let route = route_link(
+                pred_points,
+                own_points,
+                task_start_and_end_points,
+                &options.theme,
+            );
+            let mut path = Path::new()
+                .set("class", format!("link {}", kind.name()))
                 .set("fill", "none")
-                .set("stroke", "blue")
-                .set("stroke-width", "1")
-                .set("d", data);
+                .set("stroke", kind.color(options))
+                .set(
+                    "stroke-width",
+                    if kind == LinkKind::Critical {
+                        "1.5"
+                    } else {
+                        "1"
+                    },
+                )
+                .set("marker-end", format!("url(#arrow-{})", kind.name()))
+                .set("d", link_path(&route));
+            if kind == LinkKind::Slack {
+                path = path.set("stroke-dasharray", "2 2");
+            }
             the_doc = the_doc.add(path);
+            routes.push(route);
         }
     }
-    the_doc
+    (the_doc, routes)
 }
 
-fn select_color_for_load(load: f32) -> String {
-    let color = match load {
-        x if (0.0..=0.25).contains(&x) => "#00C000",
-        x if (0.26..=0.5).contains(&x) => "#00FF00",
-        x if (0.51..=0.75).contains(&x) => "#FFFF00",
-        x if (0.75..=1.0).contains(&x) => "#FF0000",
-        x if x > 1.00 => "#A00000",
-        _ => "#000000",
-    };
-    color.to_string()
+/// The colours of the default load bands, from idle to overloaded.
+pub(crate) const LOAD_COLORS: [&str; 5] = ["#00C000", "#00FF00", "#FFFF00", "#FF0000", "#A00000"];
+
+/// Categorical colours for tasks without a colour of their own in the load
+/// histogram.
+const TASK_PALETTE: [&str; 8] = [
+    "#4E79A7", "#F28E2B", "#59A14F", "#E15759", "#76B7B2", "#EDC948", "#B07AA1", "#9C755F",
+];
+
+/// The colour of the task's part of the load histogram: its own, the one of
+/// its tag or one from the palette.
+fn task_color<'a>(p: &'a Project, task_id: u32, theme: &'a Theme) -> &'a str {
+    let index = p.tasks.iter().position(|x| x.id == task_id).unwrap_or(0);
+    let task = &p.tasks[index];
+    match (&task.color, theme.tag_rule(&task.tags)) {
+        (Some(color), _) => color,
+        (None, Some(rule)) => &theme.tag_colors[rule].color,
+        (None, None) => TASK_PALETTE[index % TASK_PALETTE.len()],
+    }
 }
 
-pub fn render_resource_load_chart(p: Project, start_date: Date) -> Document {
-    let mut document = render_gantt_layout(start_date);
-    for (resource_number, res) in p.resources.iter().enumerate() {
+/// The rows of a resource in the load histogram.
+const HISTOGRAM_ROWS: usize = 4;
+
+pub fn render_resource_load_chart(
+    p: Project,
+    start_date: Date,
+    options: &RenderOptions,
+) -> Document {
+    let theme = &options.theme;
+    let column = label_column_width(
+        p.resources.iter().map(|x| text_width(&x.label, theme)),
+        options,
+    );
+    let scale = TimeScale::new(options, start_date, start_date, column);
+    let dates = p.task_dates();
+    let runs: Vec<Vec<LoadRun>> = p
+        .resources
+        .iter()
+        .map(|res| load_runs(&p, &dates, res.id, &scale, options.load_period))
+        .collect();
+    let rows_per_resource = match options.load_view {
+        LoadView::Bands => 1,
+        LoadView::Histogram => HISTOGRAM_ROWS,
+    };
+    let (mut document, bar_start_y, area) = render_gantt_layout(
+        &scale,
+        options,
+        "Resource",
+        p.resources.len() * rows_per_resource,
+        &p.holidays,
+    );
+    // The histogram fits the highest load, but shows at least full capacity.
+    // Work without any capacity fills the row.
+    let max_load = runs
+        .iter()
+        .flatten()
+        .map(|x| x.total())
+        .filter(|x| x.is_finite())
+        .fold(1.0, f32::max);
+    let mut shown_tasks = BTreeSet::new();
+
+    for (resource_number, (res, runs)) in p.resources.iter().zip(runs.iter()).enumerate() {
         let element_y =
-            BAR_START_Y + BAR_HEIGHT * resource_number as u32 + 2 * resource_number as u32;
-        document = add_text_at(document, &res.label, 0, element_y + 6);
-        document = add_h_line(document, element_y - 1);
-        // We calculate the load of each resource at the start of the
-        // week to avoid having too many objects
-        for day in 0..365 {
-            let the_date = start_date.checked_add(time::Duration::days(day)).unwrap();
-            let load = p.calculate_resource_load(res.id, the_date);
-
-            let element_x = date_to_x_pos(start_date, the_date);
+            bar_start_y + theme.row_height() * (resource_number * rows_per_resource) as f32;
+        document = add_column_label(
+            document,
+            &res.label,
+            (0.0, theme.label_y(element_y)),
+            column,
+            theme,
+        );
+        document = add_h_line(document, element_y - theme.row_spacing / 2.0, &area, theme);
+
+        let height = theme.row_height() * rows_per_resource as f32 - theme.row_spacing;
+        for run in runs.iter() {
+            let x = scale.x(run.first);
+            let width = scale.x(run.after_last) - x;
+            match options.load_view {
+                LoadView::Bands => {
+                    let color = theme.load_color(run.total());
+                    document = document.add(
+                        element::Rectangle::new()
+                            .set("x", x)
+                            .set("y", element_y)
+                            .set("width", width)
+                            .set("height", height)
+                            .set("fill", color)
+                            .set("stroke", color)
+                            .set("stroke-width", "1"),
+                    );
+                }
+                LoadView::Histogram => {
+                    let mut top = element_y + height;
+                    for (task_id, load) in run.task_loads.iter() {
+                        let segment = (height * load / max_load).min(top - element_y);
+                        top -= segment;
+                        let task = p.tasks.iter().find(|x| x.id == *task_id).unwrap();
+                        document = document.add(
+                            element::Rectangle::new()
+                                .set("class", "load-segment")
+                                .set("x", x)
+                                .set("y", top)
+                                .set("width", width)
+                                .set("height", segment)
+                                .set("fill", task_color(&p, *task_id, theme))
+                                .add(element::Title::new().add(svg::node::Text::new(format!(
+                                    "{}: {}",
+                                    task.label,
+                                    load_percent(*load)
+                                )))),
+                        );
+                        shown_tasks.insert(*task_id);
+                    }
+                }
+            }
+        }
+
+        if options.load_view == LoadView::Histogram {
+            let capacity_y = element_y + height - height / max_load;
             let data = Data::new()
-                .move_to((element_x, element_y))
-                .line_by((1, 0))
-                .line_by((0, BAR_HEIGHT))
-                .line_by((-1, 0))
-                .close();
+                .move_to((scale.left, capacity_y))
+                .horizontal_line_to(area.width);
+            document = document.add(
+                Path::new()
+                    .set("class", "capacity")
+                    .set("fill", "none")
+                    .set("stroke", theme.grid.as_str())
+                    .set("stroke-width", "0.5")
+                    .set("stroke-dasharray", "3 2")
+                    .set("d", data),
+            );
+        }
+    }
 
-            let _path = Path::new()
-                .set("fill", select_color_for_load(load))
-                .set("stroke", select_color_for_load(load))
-                .set("stroke-width", "1")
-                .set("d", data);
-            document = document.add(_path);
+    let (document, bottom_edge) = render_status_line(document, &scale, start_date, &area, options);
+    let band_labels = theme.load_band_labels();
+    let legend: Vec<(&str, &str)> = match options.load_view {
+        LoadView::Bands => band_labels
+            .iter()
+            .zip(theme.load_bands.iter())
+            .map(|(label, band)| (label.as_str(), band.color.as_str()))
+            .collect(),
+        LoadView::Histogram => p
+            .tasks
+            .iter()
+            .filter(|x| shown_tasks.contains(&x.id))
+            .map(|x| (x.label.as_str(), task_color(&p, x.id, theme)))
+            .collect(),
+    };
+    let (document, bottom_edge) = render_legend(document, &legend, bottom_edge, area.width, theme);
+    set_canvas_size(document, area.width, bottom_edge)
+}
+
+/// The label of the resources in no team.
+const NO_TEAM: &str = "No team";
+
+/// A team's demand and capacity over a period, in hours per working day.
+#[derive(Debug, PartialEq)]
+struct TeamPeriod {
+    first: Date,
+    after_last: Date,
+    demand: f32,
+    capacity: f32,
+}
+
+/// The demand on the team and its capacity per period of the unit.
+fn team_periods(
+    p: &Project,
+    dates: &TaskDates,
+    team: Option<&str>,
+    scale: &TimeScale,
+    unit: TimeUnit,
+) -> Vec<TeamPeriod> {
+    load_periods(scale, unit)
+        .into_iter()
+        .map(|(first, after_last)| {
+            let (mut demand, mut capacity, mut workdays) = (0.0, 0.0, 0);
+            let mut day = first;
+            while day < after_last {
+                demand += p.calculate_team_demand(dates, team, day);
+                capacity += p.team_capacity_on(team, day);
+                workdays += is_workday(day, &p.holidays) as u32;
+                day += time::Duration::days(1);
+            }
+            let workdays = workdays.max(1) as f32;
+            TeamPeriod {
+                first,
+                after_last,
+                demand: demand / workdays,
+                capacity: capacity / workdays,
+            }
+        })
+        .collect()
+}
+
+/// Draws the demand on each team as an area, with its capacity as a line and
+/// the demand over it highlighted. Each team has its own vertical scale, whose
+/// top is given below the team's label.
+pub fn render_team_demand_chart(
+    p: &Project,
+    start_date: Date,
+    options: &RenderOptions,
+) -> Document {
+    let theme = &options.theme;
+    let mut teams: Vec<Option<&str>> = p.teams().into_iter().map(Some).collect();
+    if !p.team_members(None).is_empty() {
+        teams.push(None);
+    }
+    let labels: Vec<&str> = teams.iter().map(|x| x.unwrap_or(NO_TEAM)).collect();
+    // The periods do not depend on the label column.
+    let window = TimeScale::new(options, start_date, start_date, 0.0);
+    let dates = p.task_dates();
+    let periods: Vec<Vec<TeamPeriod>> = teams
+        .iter()
+        .map(|team| team_periods(p, &dates, *team, &window, options.load_period))
+        .collect();
+    let maxima: Vec<f32> = periods
+        .iter()
+        .map(|x| {
+            x.iter()
+                .flat_map(|x| [x.demand, x.capacity])
+                .fold(0.0, f32::max)
+        })
+        .collect();
+    let scale_labels: Vec<String> = maxima
+        .iter()
+        .map(|max| format!("max {:.0} h/day", max))
+        .collect();
+    let column = label_column_width(
+        labels
+            .iter()
+            .map(|x| text_width(x, theme))
+            .chain(scale_labels.iter().map(|x| text_width(x, theme))),
+        options,
+    );
+    let scale = TimeScale::new(options, start_date, start_date, column);
+    let (mut document, bar_start_y, area) = render_gantt_layout(
+        &scale,
+        options,
+        "Team",
+        teams.len() * HISTOGRAM_ROWS,
+        &p.holidays,
+    );
+    let over_color = theme.load_color(f32::INFINITY);
+
+    for (number, label) in labels.iter().enumerate() {
+        let top = bar_start_y + theme.row_height() * (number * HISTOGRAM_ROWS) as f32;
+        let height = theme.row_height() * HISTOGRAM_ROWS as f32 - theme.row_spacing;
+        let bottom = top + height;
+        document = add_h_line(document, top - theme.row_spacing / 2.0, &area, theme);
+        document = add_column_label(document, label, (0.0, theme.label_y(top)), column, theme);
+
+        let (periods, max) = (&periods[number], maxima[number]);
+        if max <= 0.0 {
+            continue;
+        }
+        document = add_column_label(
+            document,
+            &scale_labels[number],
+            (0.0, theme.label_y(top + theme.row_height())),
+            column,
+            theme,
+        );
+        let y = |hours: f32| bottom - height * hours / max;
+
+        let mut demand = Data::new().move_to((scale.x(periods[0].first), bottom));
+        let mut capacity = Data::new().move_to((scale.x(periods[0].first), y(periods[0].capacity)));
+        let mut overloads = vec![];
+        for period in periods.iter() {
+            let (x, end_x) = (scale.x(period.first), scale.x(period.after_last));
+            demand = demand
+                .vertical_line_to(y(period.demand))
+                .horizontal_line_to(end_x);
+            capacity = capacity
+                .vertical_line_to(y(period.capacity))
+                .horizontal_line_to(end_x);
+            if period.demand > period.capacity + 0.001 {
+                overloads.push(
+                    element::Rectangle::new()
+                        .set("class", "team-overload")
+                        .set("x", x)
+                        .set("y", y(period.demand))
+                        .set("width", end_x - x)
+                        .set("height", y(period.capacity) - y(period.demand))
+                        .set("fill", over_color)
+                        .add(element::Title::new().add(svg::node::Text::new(format!(
+                            "{}: {:.1} h of {:.1} h per day",
+                            label, period.demand, period.capacity
+                        )))),
+                );
+            }
         }
+        document = document.add(
+            Path::new()
+                .set("class", "team-demand")
+                .set("fill", theme.bar_fill.as_str())
+                .set("fill-opacity", 0.6)
+                .set("stroke", theme.bar_stroke.as_str())
+                .set("stroke-width", "0.5")
+                .set("d", demand.vertical_line_to(bottom).close()),
+        );
+        for overload in overloads {
+            document = document.add(overload);
+        }
+        document = document.add(
+            Path::new()
+                .set("class", "capacity")
+                .set("fill", "none")
+                .set("stroke", theme.text.as_str())
+                .set("stroke-width", "1")
+                .set("stroke-dasharray", "3 2")
+                .set("d", capacity),
+        );
     }
 
-    document
+    let (document, bottom_edge) = render_status_line(document, &scale, start_date, &area, options);
+    let legend = [
+        ("Demand", theme.bar_fill.as_str()),
+        ("Capacity", theme.text.as_str()),
+        ("Over capacity", over_color),
+    ];
+    let (document, bottom_edge) = render_legend(document, &legend, bottom_edge, area.width, theme);
+    set_canvas_size(document, area.width, bottom_edge)
 }
 
 #[cfg(test)]
 mod tests {
     use chrono::NaiveDate;
+    use time::{macros::date, Date};
 
+    use crate::pom::fixtures::{allocation, project, resource, task};
+    use crate::pom::Project;
     use crate::renderer::next_quarter;
+    use crate::theme::{LoadBand, TagColor, Theme};
+
+    use std::collections::HashMap;
+
+    use super::{
+        allocation_label, auto_header, font, free_position, link_crossings, link_path, load_runs,
+        render_gantt, render_resource_load_chart, render_swimlanes, render_team_demand_chart,
+        route_link, start_of_quarter, task_label, team_periods, LoadView, Point, RenderOptions,
+        TaskPoints, TimeScale, TimeUnit,
+    };
+
+    fn make_project() -> Project {
+        project(
+            vec![task(0, "Sprint", 40, date!(2023 - 06 - 12), vec![])],
+            vec![],
+            vec![],
+        )
+    }
 
-    use super::start_of_quarter;
+    /// A finished, a running and an upcoming task, plus a long one after the
+    /// finished task.
+    fn make_history_project() -> Project {
+        project(
+            vec![
+                task(0, "Done", 40, date!(2023 - 05 - 01), vec![]),
+                task(1, "Running", 80, date!(2023 - 06 - 05), vec![]),
+                task(2, "Next", 40, date!(2023 - 06 - 05), vec![1]),
+                task(3, "Follow-up", 400, date!(2023 - 05 - 01), vec![0]),
+            ],
+            vec![],
+            vec![],
+        )
+    }
 
     #[test]
     pub fn get_correct_start_of_quarter() {
@@ -311,4 +1726,546 @@ mod tests {
             NaiveDate::from_ymd_opt(2023, 7, 1).unwrap()
         )
     }
+
+    #[test]
+    pub fn picks_header_from_day_width() {
+        assert_eq!(auto_header(1.0), vec![TimeUnit::Quarter, TimeUnit::Month]);
+        assert_eq!(auto_header(4.0), vec![TimeUnit::Month, TimeUnit::Week]);
+        assert_eq!(auto_header(12.0), vec![TimeUnit::Month, TimeUnit::Day]);
+        assert_eq!(auto_header(0.05), vec![TimeUnit::Year]);
+    }
+
+    #[test]
+    pub fn finds_unit_boundaries() {
+        let d = NaiveDate::from_ymd_opt(2023, 6, 8).unwrap();
+
+        assert_eq!(
+            TimeUnit::Week.start_of(d),
+            NaiveDate::from_ymd_opt(2023, 6, 5).unwrap()
+        );
+        assert_eq!(
+            TimeUnit::Week.next_start(d),
+            NaiveDate::from_ymd_opt(2023, 6, 12).unwrap()
+        );
+        assert_eq!(
+            TimeUnit::Month.next_start(d),
+            NaiveDate::from_ymd_opt(2023, 7, 1).unwrap()
+        );
+        assert_eq!(
+            TimeUnit::Year.next_start(d),
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+        );
+        assert_eq!(TimeUnit::Week.label(d, true), "W23");
+        assert_eq!(TimeUnit::Quarter.label(d, false), "Q2 2023");
+    }
+
+    #[test]
+    pub fn scales_bars_to_the_window() {
+        let options = RenderOptions {
+            label_width: Some(150.0),
+            day_width: 12.0,
+            window_start: Some(date!(2023 - 06 - 05)),
+            window_end: Some(date!(2023 - 06 - 30)),
+            header: vec![TimeUnit::Week, TimeUnit::Day],
+            ..RenderOptions::default()
+        };
+        let svg = render_gantt(&make_project(), date!(2023 - 06 - 08), &options).to_string();
+
+        // A week of work from Monday the 12th, i.e. 7 days after the window start.
+        assert!(svg.contains("M234,22 l84,0 l0,8 l-84,0 z"));
+        assert!(svg.contains(">\nW24\n<"));
+        assert!(svg.contains(">\n30\n<"));
+        assert!(!svg.contains(">\nJul"));
+    }
+
+    #[test]
+    pub fn sizes_canvas_to_rows_and_window() {
+        let options = RenderOptions {
+            label_width: Some(150.0),
+            day_width: 12.0,
+            window_start: Some(date!(2023 - 06 - 05)),
+            window_end: Some(date!(2023 - 06 - 30)),
+            header: vec![TimeUnit::Week, TimeUnit::Day],
+            ..RenderOptions::default()
+        };
+        let svg = render_gantt(&make_project(), date!(2023 - 06 - 08), &options).to_string();
+
+        // 26 days of 12 units after the label column, one row below two tiers
+        // and the status date label below that
+        assert!(svg.contains("viewBox=\"0 0 464 43\""));
+        assert!(svg.contains("width=\"464\""));
+        assert!(svg.contains("M0,31 l462,0 z"));
+        assert!(svg.contains("M150,0 l0,31 z"));
+        assert!(!svg.contains("5000"));
+    }
+
+    #[test]
+    pub fn widens_canvas_for_labels_behind_the_window() {
+        let options = RenderOptions {
+            label_width: Some(150.0),
+            day_width: 1.0,
+            window_start: Some(date!(2023 - 06 - 05)),
+            window_end: Some(date!(2023 - 06 - 16)),
+            header: vec![TimeUnit::Month],
+            ..RenderOptions::default()
+        };
+        let svg = render_gantt(&make_project(), date!(2023 - 06 - 08), &options).to_string();
+
+        // The bar ends at the window, its label "FTE:0/1" sticks out.
+        assert!(svg.contains("viewBox=\"0 0 197 33\""));
+    }
+
+    #[test]
+    pub fn skips_finished_tasks_and_clips_running_ones() {
+        let svg = render_gantt(
+            &make_history_project(),
+            date!(2023 - 06 - 12),
+            &RenderOptions::default(),
+        )
+        .to_string();
+
+        assert!(!svg.contains("Done"));
+        assert!(svg.contains("Running"));
+        // Running and Follow-up started before the window.
+        assert_eq!(svg.matches("class=\"continuation\"").count(), 2);
+        assert!(!svg.contains("#D0D0D0\" stroke=\"#A0A0A0"));
+    }
+
+    #[test]
+    pub fn greys_out_history_in_the_window() {
+        let options = RenderOptions {
+            label_width: Some(150.0),
+            window_start: Some(date!(2023 - 05 - 01)),
+            window_end: Some(date!(2023 - 06 - 30)),
+            ..RenderOptions::default()
+        };
+        let svg =
+            render_gantt(&make_history_project(), date!(2023 - 06 - 12), &options).to_string();
+
+        assert!(svg.contains("Done"));
+        // All of Done and the past parts of Running and Follow-up
+        assert_eq!(svg.matches("fill=\"#D0D0D0\"").count(), 3);
+        assert_eq!(svg.matches("fill=\"#A0A0CC\"").count(), 3);
+        // Follow-up runs on after the window.
+        assert_eq!(svg.matches("class=\"continuation\"").count(), 1);
+    }
+
+    #[test]
+    pub fn draws_status_line_and_non_working_days() {
+        let mut p = make_project();
+        p.holidays = vec![date!(2023 - 06 - 14), date!(2023 - 08 - 01)];
+        let mut options = RenderOptions {
+            label_width: Some(150.0),
+            day_width: 12.0,
+            window_start: Some(date!(2023 - 06 - 05)),
+            window_end: Some(date!(2023 - 06 - 30)),
+            ..RenderOptions::default()
+        };
+        options.theme.status_line = "#00AA00".to_string();
+        let svg = render_gantt(&p, date!(2023 - 06 - 08), &options).to_string();
+
+        // Thursday the 8th is three days after the window start.
+        assert!(svg.contains("d=\"M186,0 L186,33 z\""));
+        assert!(svg.contains("stroke=\"#00AA00\""));
+        assert!(svg.contains(">\nStatus 2023-06-08\n<"));
+        // Three weekends in the window, Saturday the 10th to Sunday the 11th first
+        assert_eq!(svg.matches("fill=\"#F0F0F0\"").count(), 3);
+        assert!(
+            svg.contains("<rect fill=\"#F0F0F0\" height=\"11\" width=\"24\" x=\"210\" y=\"20\"/>")
+        );
+        // Only the holiday inside the window
+        assert_eq!(svg.matches("fill=\"#FFE0E0\"").count(), 1);
+    }
+
+    #[test]
+    pub fn leaves_out_status_line_outside_the_window() {
+        let options = RenderOptions {
+            label_width: Some(150.0),
+            window_start: Some(date!(2023 - 06 - 12)),
+            ..RenderOptions::default()
+        };
+        let svg = render_gantt(&make_project(), date!(2023 - 06 - 08), &options).to_string();
+
+        assert!(!svg.contains("status-date"));
+        // Too narrow for weekends
+        assert!(!svg.contains("#F0F0F0"));
+    }
+
+    #[test]
+    pub fn applies_the_theme() {
+        let mut theme = Theme::preset("dark").unwrap();
+        theme.font_size = 10.0;
+        theme.bar_height = 12.0;
+        theme.row_spacing = 4.0;
+        let options = RenderOptions {
+            label_width: Some(150.0),
+            day_width: 12.0,
+            window_start: Some(date!(2023 - 06 - 05)),
+            window_end: Some(date!(2023 - 06 - 30)),
+            header: vec![TimeUnit::Week, TimeUnit::Day],
+            theme,
+            ..RenderOptions::default()
+        };
+        let svg = render_gantt(&make_project(), date!(2023 - 06 - 08), &options).to_string();
+
+        assert!(svg.contains("fill=\"#1E1E1E\" height=\"100%\""));
+        assert!(svg.contains("font-size=\"10\""));
+        assert!(!svg.contains("font-size=\"8\""));
+        assert!(svg.contains("fill=\"#E0E0E0\""));
+        // Two tiers of 12 units, the bar 12 high in a row of 16
+        assert!(svg.contains("M234,26 l84,0 l0,12 l-84,0 z"));
+        assert!(svg.contains("fill=\"#4A5A9A\""));
+        assert!(svg.contains("M0,40 l462,0 z"));
+    }
+
+    #[test]
+    pub fn colours_bars_by_tag_and_lists_used_tags() {
+        let mut p = make_history_project();
+        p.tasks[1].tags = vec!["backend".to_string()];
+        p.tasks[2].tags = vec!["ui".to_string(), "backend".to_string()];
+        p.tasks[2].color = Some("#123456".to_string());
+        p.tasks[3].tags = vec!["ui".to_string()];
+        let mut options = RenderOptions::default();
+        for (tag, color) in [
+            ("ui", "#E69F00"),
+            ("backend", "#0072B2"),
+            ("ops", "#009E73"),
+        ] {
+            options.theme.tag_colors.push(TagColor {
+                tag: tag.to_string(),
+                color: color.to_string(),
+            });
+        }
+        let svg = render_gantt(&p, date!(2023 - 06 - 12), &options).to_string();
+
+        assert!(svg.contains("fill=\"#123456\" stroke=\"#123456\""));
+        assert!(svg.contains("fill=\"#0072B2\" stroke=\"#0072B2\""));
+        assert!(svg.contains("fill=\"#E69F00\" stroke=\"#E69F00\""));
+        assert!(!svg.contains("#A0A0CC"));
+        // Only the rules of visible bars, in the order of the rules
+        let legend = &svg[svg.find("class=\"legend\"").unwrap()..];
+        assert!(legend.find(">\nui\n<").unwrap() < legend.find(">\nbackend\n<").unwrap());
+        assert!(!svg.contains("ops"));
+
+        let svg = render_gantt(&make_project(), date!(2023 - 06 - 08), &options).to_string();
+        assert!(!svg.contains("legend"));
+    }
+
+    #[test]
+    pub fn fits_the_label_column_to_the_labels() {
+        let svg = render_gantt(
+            &make_project(),
+            date!(2023 - 06 - 08),
+            &RenderOptions::default(),
+        )
+        .to_string();
+        let label = task_label(
+            &make_project(),
+            &make_project().tasks[0],
+            date!(2023 - 06 - 08),
+        );
+        let column = (font::text_width(&label, 8.0) + 4.0).ceil();
+
+        assert!(svg.contains(&format!("d=\"M{},0 l0,", column)));
+        assert!(!svg.contains("<title>"));
+    }
+
+    #[test]
+    pub fn shortens_labels_wider_than_the_column() {
+        let options = RenderOptions {
+            label_width: Some(40.0),
+            ..RenderOptions::default()
+        };
+        let svg =
+            render_gantt(&make_history_project(), date!(2023 - 05 - 01), &options).to_string();
+
+        assert!(svg.contains("d=\"M40,0 l0,"));
+        assert!(svg.contains("<title>\nFollow-up, 50 days, 1 FTE\n</title>"));
+        assert!(svg.contains("Follow…"));
+    }
+
+    #[test]
+    pub fn places_resource_labels_right_of_crossing_links() {
+        // A link leaving the row at 200 and running down at 204
+        let routes = vec![vec![
+            (200.0, 24.0),
+            (204.0, 24.0),
+            (204.0, 54.0),
+            (260.0, 54.0),
+        ]];
+        let theme = Theme::default();
+        let crossings = link_crossings(&routes, 20.0, &theme);
+        assert_eq!(free_position(202.0, 30.0, &crossings), 205.0);
+
+        // In a row further down only the vertical segment crosses.
+        let crossings = link_crossings(&routes, 30.0, &theme);
+        assert_eq!(crossings, vec![(203.0, 205.0)]);
+        assert_eq!(free_position(170.0, 40.0, &crossings), 205.0);
+        assert_eq!(free_position(170.0, 20.0, &crossings), 170.0);
+        assert!(link_crossings(&routes, 60.0, &theme).is_empty());
+    }
+
+    #[test]
+    pub fn groups_tasks_into_lanes_by_resource() {
+        let mut p = make_history_project();
+        p.resources = vec![resource(0, "Hans"), resource(1, "Ernst")];
+        p.allocations = vec![
+            allocation(1, 0, 1.5),
+            allocation(2, 0, 0.5),
+            allocation(2, 1, 1.0 / 3.0),
+        ];
+        let options = RenderOptions {
+            label_width: Some(150.0),
+            window_start: Some(date!(2023 - 06 - 01)),
+            window_end: Some(date!(2023 - 06 - 30)),
+            ..RenderOptions::default()
+        };
+        let svg = render_swimlanes(&p, date!(2023 - 06 - 12), &options).to_string();
+
+        let hans = svg.find(">\nHans\n<").unwrap();
+        let ernst = svg.find(">\nErnst\n<").unwrap();
+        let unassigned = svg.find(">\nUnassigned\n<").unwrap();
+        assert!(hans < ernst && ernst < unassigned);
+        // Next is in both lanes, Follow-up nobody's.
+        assert_eq!(svg.matches(">\nNext, ").count(), 2);
+        assert!(svg[unassigned..].contains(">\nFollow-up, "));
+        assert!(svg.contains(">\n50%\n<"));
+        assert!(svg.contains(">\n33%\n<"));
+        assert!(svg.contains(">\nFTE:0/1\n<"));
+        // Hans is busy the whole window and overloaded while Running runs.
+        assert!(svg.contains("class=\"lane-load\" fill=\"#A00000\""));
+        assert!(!svg.contains("class=\"link"));
+    }
+
+    #[test]
+    pub fn rounds_the_allocated_loads() {
+        let mut p = make_loaded_project();
+        p.allocations[1].load = 1.0 / 3.0;
+
+        assert!(allocation_label(&p, &p.tasks[2]).starts_with("Hans:33%, FTE:"));
+    }
+
+    /// The history project with Running and Next on one resource.
+    fn make_loaded_project() -> Project {
+        let mut p = make_history_project();
+        p.resources = vec![resource(0, "Hans")];
+        p.allocations = vec![allocation(1, 0, 1.0), allocation(2, 0, 0.5)];
+        p
+    }
+
+    #[test]
+    pub fn averages_load_over_periods_and_merges_runs() {
+        let p = make_loaded_project();
+        let options = RenderOptions {
+            window_start: Some(date!(2023 - 06 - 01)),
+            window_end: Some(date!(2023 - 07 - 31)),
+            ..RenderOptions::default()
+        };
+        let scale = TimeScale::new(&options, date!(2023 - 06 - 01), date!(2023 - 06 - 01), 0.0);
+
+        // Running from Monday the 5th to the 19th and Next from then on until
+        // the 26th, both worked on from the day after their start like
+        // calculate_resource_load counts, at full capacity on weekdays only
+        let runs = load_runs(&p, &p.task_dates(), 0, &scale, TimeUnit::Day);
+        let bounds: Vec<(Date, Date, f32)> = runs
+            .iter()
+            .map(|x| (x.first, x.after_last, x.total()))
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                (date!(2023 - 06 - 01), date!(2023 - 06 - 06), 0.0),
+                (date!(2023 - 06 - 06), date!(2023 - 06 - 10), 1.0),
+                (date!(2023 - 06 - 10), date!(2023 - 06 - 12), 0.0),
+                (date!(2023 - 06 - 12), date!(2023 - 06 - 17), 1.0),
+                (date!(2023 - 06 - 17), date!(2023 - 06 - 19), 0.0),
+                (date!(2023 - 06 - 19), date!(2023 - 06 - 20), 1.0),
+                (date!(2023 - 06 - 20), date!(2023 - 06 - 24), 0.5),
+                (date!(2023 - 06 - 24), date!(2023 - 06 - 26), 0.0),
+                (date!(2023 - 06 - 26), date!(2023 - 06 - 27), 0.5),
+                (date!(2023 - 06 - 27), date!(2023 - 08 - 01), 0.0),
+            ]
+        );
+
+        let runs = load_runs(&p, &p.task_dates(), 0, &scale, TimeUnit::Week);
+        // The first week is clipped to the window, the idle weeks of July
+        // are merged. Loads are the hours of work over the 40 the resource
+        // works in a week.
+        assert_eq!(runs[0].first, date!(2023 - 06 - 01));
+        assert_eq!(runs[0].after_last, date!(2023 - 06 - 05));
+        assert_eq!(runs[1].after_last, date!(2023 - 06 - 12));
+        assert_eq!(runs[1].task_loads, vec![(1, 32.0 / 40.0)]);
+        assert_eq!(runs[2].task_loads, vec![(1, 1.0)]);
+        assert_eq!(runs[3].task_loads, vec![(1, 8.0 / 40.0), (2, 16.0 / 40.0)]);
+        assert_eq!(runs[4].task_loads, vec![(2, 4.0 / 40.0)]);
+        assert_eq!(runs.len(), 6);
+        assert_eq!(runs.last().unwrap().after_last, date!(2023 - 08 - 01));
+
+        let runs = load_runs(&p, &p.task_dates(), 0, &scale, TimeUnit::Month);
+        assert_eq!(runs.len(), 2);
+        // 22 weekdays in June
+        assert_eq!(
+            runs[0].task_loads,
+            vec![(1, 80.0 / 176.0), (2, 20.0 / 176.0)]
+        );
+    }
+
+    #[test]
+    pub fn stacks_the_load_by_task() {
+        let options = RenderOptions {
+            window_start: Some(date!(2023 - 06 - 01)),
+            horizon_days: 30,
+            load_view: LoadView::Histogram,
+            ..RenderOptions::default()
+        };
+        let svg =
+            render_resource_load_chart(make_loaded_project(), date!(2023 - 06 - 12), &options)
+                .to_string();
+
+        // Running alone in three runs up to the 19th, then Next alone before
+        // and after the weekend
+        assert_eq!(svg.matches("class=\"load-segment\"").count(), 5);
+        assert!(svg.contains("<title>\nNext: 50%\n</title>"));
+        assert_eq!(svg.matches("class=\"capacity\"").count(), 1);
+        assert!(svg.contains(">\nRunning\n<"));
+        // 30 days from the window start
+        assert!(svg.contains(">\nJun\n<") && !svg.contains(">\nJul\n<"));
+    }
+
+    #[test]
+    pub fn colours_the_load_relative_to_capacity() {
+        let mut p = make_loaded_project();
+        // Half time, so Next alone is a full load
+        p.resources[0].output = 20.0;
+        p.holidays = vec![date!(2023 - 06 - 07)];
+        let options = RenderOptions {
+            window_start: Some(date!(2023 - 06 - 01)),
+            horizon_days: 30,
+            theme: Theme {
+                load_bands: vec![
+                    LoadBand {
+                        up_to: Some(0.99),
+                        color: "green".to_string(),
+                    },
+                    LoadBand {
+                        up_to: Some(1.0),
+                        color: "yellow".to_string(),
+                    },
+                    LoadBand {
+                        up_to: None,
+                        color: "red".to_string(),
+                    },
+                ],
+                ..Theme::default()
+            },
+            ..RenderOptions::default()
+        };
+        let scale = TimeScale::new(&options, date!(2023 - 06 - 01), date!(2023 - 06 - 01), 0.0);
+        let runs = load_runs(&p, &p.task_dates(), 0, &scale, TimeUnit::Day);
+        let holiday = runs
+            .iter()
+            .find(|x| x.first == date!(2023 - 06 - 07))
+            .unwrap();
+        assert_eq!(holiday.total(), 0.0);
+        // The holiday moves Running's end, and Next, a day later.
+        let next = runs
+            .iter()
+            .find(|x| x.first == date!(2023 - 06 - 21))
+            .unwrap();
+        assert_eq!(next.total(), 1.0);
+
+        let svg = render_resource_load_chart(p, date!(2023 - 06 - 12), &options).to_string();
+        assert!(svg.contains("fill=\"yellow\""));
+        // Running alone on the 20th
+        assert!(svg.contains("fill=\"red\""));
+        // The legend shows the bands.
+        assert!(svg.contains(">\n0-99%\n<"));
+        assert!(svg.contains(">\n99-100%\n<"));
+        assert!(svg.contains(">\nover 100%\n<"));
+    }
+
+    #[test]
+    pub fn compares_team_demand_with_capacity() {
+        let mut p = make_loaded_project();
+        p.resources[0].team = Some("Apps".to_string());
+        p.resources.push(resource(1, "Ernst"));
+        p.allocations[0].load = 1.5;
+        let options = RenderOptions {
+            window_start: Some(date!(2023 - 06 - 01)),
+            horizon_days: 30,
+            ..RenderOptions::default()
+        };
+        let scale = TimeScale::new(&options, date!(2023 - 06 - 01), date!(2023 - 06 - 01), 0.0);
+
+        let apps = team_periods(&p, &p.task_dates(), Some("Apps"), &scale, TimeUnit::Day);
+        let period = |first: Date| apps.iter().find(|x| x.first == first).unwrap();
+        // Running at 150% on Monday the 19th, nobody works on the weekend
+        assert_eq!(period(date!(2023 - 06 - 19)).demand, 12.0);
+        assert_eq!(period(date!(2023 - 06 - 19)).capacity, 8.0);
+        assert_eq!(period(date!(2023 - 06 - 17)).capacity, 0.0);
+        // Per working day of the week
+        let weeks = team_periods(&p, &p.task_dates(), Some("Apps"), &scale, TimeUnit::Week);
+        assert_eq!(weeks[1].demand, 48.0 / 5.0);
+        assert_eq!(weeks[3].demand, 28.0 / 5.0);
+        let no_team = team_periods(&p, &p.task_dates(), None, &scale, TimeUnit::Week);
+        assert_eq!((no_team[1].demand, no_team[1].capacity), (0.0, 8.0));
+
+        let svg = render_team_demand_chart(&p, date!(2023 - 06 - 12), &options).to_string();
+        assert!(svg.contains(">\nApps\n<"));
+        assert!(svg.contains(">\nNo team\n<"));
+        assert!(svg.contains(">\nmax 12 h/day\n<"));
+        assert_eq!(svg.matches("class=\"team-demand\"").count(), 2);
+        // Every working day of Running
+        assert_eq!(svg.matches("class=\"team-overload\"").count(), 10);
+        assert!(svg.contains("<title>\nApps: 12.0 h of 8.0 h per day\n</title>"));
+        assert!(svg.contains(">\nOver capacity\n<"));
+    }
+
+    fn points(x: f32, end_x: f32, y: f32) -> TaskPoints {
+        TaskPoints {
+            start: Point { x, y },
+            end: Point { x: end_x, y },
+        }
+    }
+
+    #[test]
+    pub fn routes_links_around_bars() {
+        let from = points(150.0, 200.0, 20.0);
+        let to = points(260.0, 300.0, 40.0);
+        let mut rows = HashMap::new();
+        // The bar in between covers the first channels right of the predecessor.
+        rows.insert(1, points(180.0, 230.0, 30.0));
+
+        let route = route_link(&from, &to, &rows, &Theme::default());
+        assert_eq!(
+            svg::node::Value::from(link_path(&route)).to_string(),
+            "M200,24 H234 V44 H260"
+        );
+
+        // The successor starts where the predecessor ends, the link turns back
+        // in the gap above it.
+        let to = points(200.0, 250.0, 40.0);
+        let route = route_link(&from, &to, &rows, &Theme::default());
+        assert_eq!(
+            svg::node::Value::from(link_path(&route)).to_string(),
+            "M200,24 H234 V39 H196 V44 H200"
+        );
+    }
+
+    #[test]
+    pub fn styles_links_by_kind() {
+        let svg = render_gantt(
+            &make_history_project(),
+            date!(2023 - 05 - 01),
+            &RenderOptions::default(),
+        )
+        .to_string();
+
+        assert!(svg.contains("<marker id=\"arrow-critical\""));
+        // Done drives Follow-up, which ends last.
+        assert_eq!(svg.matches("class=\"link critical\"").count(), 1);
+        assert!(svg.contains("marker-end=\"url(#arrow-critical)\""));
+        // Running drives Next, which is not critical.
+        assert_eq!(svg.matches("class=\"link driving\"").count(), 1);
+    }
 }
@@ -0,0 +1,281 @@
+//! Compares the FTE each task needs per role with what the resources
+//! allocated to it can fill, per task and per week, to staff a plan before
+//! all names are known.
+
+use serde::Serialize;
+use time::{Date, Duration};
+
+use crate::pom::{is_workday, Project, Task};
+
+#[derive(Debug, Serialize)]
+pub struct StaffingReport {
+    /// The tasks with a role demand.
+    pub tasks: Vec<TaskStaffing>,
+    /// The weeks with unfilled demand.
+    pub weeks: Vec<WeekStaffing>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskStaffing {
+    pub id: u32,
+    pub label: String,
+    pub first_day: Date,
+    pub last_day: Date,
+    pub roles: Vec<RoleStaffing>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RoleStaffing {
+    pub role: String,
+    pub demand: f32,
+    pub filled: f32,
+    pub unfilled: f32,
+}
+
+/// The unfilled demand of the tasks running in the week, as FTE averaged
+/// over its five working days.
+#[derive(Debug, Serialize)]
+pub struct WeekStaffing {
+    pub monday: Date,
+    pub unfilled: Vec<UnfilledRole>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct UnfilledRole {
+    pub role: String,
+    pub fte: f32,
+}
+
+/// Splits the task's role demand into the FTE its allocations fill and the
+/// rest. An allocation fills the roles of its resource in the order the task
+/// demands them, each up to the demand, so no FTE counts twice.
+fn role_staffing(p: &Project, task: &Task) -> Vec<RoleStaffing> {
+    let mut filled = vec![0.0; task.role_demand.len()];
+    for allocation in p.allocations.iter().filter(|x| x.taskid == task.id) {
+        let Some(res) = p.resources.iter().find(|x| x.id == allocation.resourceid) else {
+            continue;
+        };
+        let mut left = allocation.load;
+        for (demand, filled) in task.role_demand.iter().zip(filled.iter_mut()) {
+            if res.roles.contains(&demand.role) {
+                let share = left.min(demand.fte - *filled).max(0.0);
+                *filled += share;
+                left -= share;
+            }
+        }
+    }
+    task.role_demand
+        .iter()
+        .zip(filled)
+        .map(|(demand, filled)| RoleStaffing {
+            role: demand.role.clone(),
+            demand: demand.fte,
+            filled: round_fte(filled),
+            unfilled: round_fte(demand.fte - filled),
+        })
+        .collect()
+}
+
+/// The task's working days in the week of the Monday, counting days like
+/// `Project::calculate_resource_load`.
+fn workdays_in_week(task: &TaskStaffing, monday: Date, holidays: &[Date]) -> u32 {
+    (0..5)
+        .map(|x| monday + Duration::days(x))
+        .filter(|day| is_workday(*day, holidays) && *day > task.first_day && *day <= task.last_day)
+        .count() as u32
+}
+
+pub fn find_unfilled_roles(p: &Project) -> StaffingReport {
+    let tasks: Vec<TaskStaffing> = p
+        .tasks
+        .iter()
+        .filter(|x| !x.role_demand.is_empty())
+        .map(|task| TaskStaffing {
+            id: task.id,
+            label: task.label.clone(),
+            first_day: task.get_actual_start_date(p),
+            last_day: task.get_actual_end_date(p),
+            roles: role_staffing(p, task),
+        })
+        .collect();
+
+    let mut weeks = vec![];
+    if let (Some(first), Some(last)) = (
+        tasks.iter().map(|x| x.first_day).min(),
+        tasks.iter().map(|x| x.last_day).max(),
+    ) {
+        let mut monday = first - Duration::days(first.weekday().number_days_from_monday() as i64);
+        while monday <= last {
+            let mut unfilled: Vec<UnfilledRole> = vec![];
+            for task in tasks.iter() {
+                let share = workdays_in_week(task, monday, &p.holidays) as f32 / 5.0;
+                for role in task.roles.iter().filter(|x| x.unfilled > 0.0) {
+                    let fte = role.unfilled * share;
+                    match unfilled.iter_mut().find(|x| x.role == role.role) {
+                        Some(existing) => existing.fte += fte,
+                        None => unfilled.push(UnfilledRole {
+                            role: role.role.clone(),
+                            fte,
+                        }),
+                    }
+                }
+            }
+            unfilled.retain(|x| x.fte > 0.0);
+            for role in unfilled.iter_mut() {
+                role.fte = round_fte(role.fte);
+            }
+            if !unfilled.is_empty() {
+                weeks.push(WeekStaffing { monday, unfilled });
+            }
+            monday += Duration::days(7);
+        }
+    }
+    StaffingReport { tasks, weeks }
+}
+
+/// Rounds to hundredths, against rounding errors in the sums.
+fn round_fte(fte: f32) -> f32 {
+    (fte * 100.0).round() / 100.0 + 0.0
+}
+
+/// Lists each task's role demand and how much of it is filled, then the
+/// unfilled demand per week.
+pub fn format_staffing_report(report: &StaffingReport) -> String {
+    if report.tasks.is_empty() {
+        return "No task states a role demand\n".to_string();
+    }
+    let mut out = "Role demand per task\n".to_string();
+    for task in report.tasks.iter() {
+        out.push_str(&format!(
+            "  {}, {} to {}\n",
+            task.label, task.first_day, task.last_day
+        ));
+        for role in task.roles.iter() {
+            out.push_str(&format!(
+                "    {}: {} FTE needed, {} filled, {} unfilled\n",
+                role.role, role.demand, role.filled, role.unfilled
+            ));
+        }
+    }
+    if report.weeks.is_empty() {
+        out.push_str("All role demand is filled\n");
+        return out;
+    }
+    out.push_str("Unfilled role demand per week\n");
+    for week in report.weeks.iter() {
+        let roles: Vec<String> = week
+            .unfilled
+            .iter()
+            .map(|x| format!("{} {} FTE", x.role, x.fte))
+            .collect();
+        out.push_str(&format!("  {}: {}\n", week.monday, roles.join(", ")));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::fixtures::{allocation, project, resource, task};
+    use crate::pom::{Project, Resource, RoleDemand, Task};
+
+    use super::{find_unfilled_roles, format_staffing_report, RoleStaffing, UnfilledRole};
+
+    fn demand(role: &str, fte: f32) -> RoleDemand {
+        RoleDemand {
+            role: role.to_string(),
+            fte,
+        }
+    }
+
+    /// Build needs backend and QA from Wednesday the 7th, Hans can do both.
+    fn make_project() -> Project {
+        let wednesday = date!(2023 - 06 - 07);
+        project(
+            vec![
+                Task {
+                    planned_resources: 2.0,
+                    role_demand: vec![demand("backend", 1.5), demand("qa", 0.5)],
+                    ..task(0, "Build", 160, wednesday, vec![])
+                },
+                task(1, "Docs", 160, wednesday, vec![]),
+            ],
+            vec![Resource {
+                roles: vec!["backend".to_string(), "qa".to_string()],
+                ..resource(0, "Hans")
+            }],
+            vec![allocation(0, 0, 1.25)],
+        )
+    }
+
+    fn role(role: &str, demand: f32, filled: f32, unfilled: f32) -> RoleStaffing {
+        RoleStaffing {
+            role: role.to_string(),
+            demand,
+            filled,
+            unfilled,
+        }
+    }
+
+    #[test]
+    pub fn fills_roles_in_the_order_of_demand() {
+        let report = find_unfilled_roles(&make_project());
+
+        assert_eq!(report.tasks.len(), 1);
+        let build = &report.tasks[0];
+        // Hans' 1.25 FTE go to backend first, nothing is left for QA.
+        assert_eq!(
+            build.roles,
+            vec![role("backend", 1.5, 1.25, 0.25), role("qa", 0.5, 0.0, 0.5)]
+        );
+        // The task runs from Wednesday to Wednesday in two weeks.
+        assert_eq!(build.last_day, date!(2023 - 06 - 21));
+        let mondays: Vec<_> = report.weeks.iter().map(|x| x.monday).collect();
+        assert_eq!(
+            mondays,
+            vec![
+                date!(2023 - 06 - 05),
+                date!(2023 - 06 - 12),
+                date!(2023 - 06 - 19)
+            ]
+        );
+        // Two of five days in the first week, work starts the day after
+        assert_eq!(
+            report.weeks[0].unfilled,
+            vec![
+                UnfilledRole {
+                    role: "backend".to_string(),
+                    fte: 0.1,
+                },
+                UnfilledRole {
+                    role: "qa".to_string(),
+                    fte: 0.2,
+                }
+            ]
+        );
+        assert_eq!(report.weeks[1].unfilled[1].fte, 0.5);
+
+        let text = format_staffing_report(&report);
+        assert!(text.contains("  Build, 2023-06-07 to 2023-06-21\n"));
+        assert!(text.contains("    backend: 1.5 FTE needed, 1.25 filled, 0.25 unfilled\n"));
+        assert!(text.contains("  2023-06-12: backend 0.25 FTE, qa 0.5 FTE\n"));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["weeks"][0]["monday"], "2023-06-05");
+    }
+
+    #[test]
+    pub fn reports_filled_demand() {
+        let mut p = make_project();
+        p.allocations[0].load = 2.0;
+        let report = find_unfilled_roles(&p);
+        assert!(report.weeks.is_empty());
+        assert!(format_staffing_report(&report).contains("All role demand is filled\n"));
+
+        p.tasks[0].role_demand.clear();
+        assert_eq!(
+            format_staffing_report(&find_unfilled_roles(&p)),
+            "No task states a role demand\n"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! TaskJuggler (.tjp) export, so a plan can be cross-checked with `tj3`.
+
+use time::Duration;
+
+use crate::pom::{Project, Resource, Task, GENERIC_RESOURCE_OUTPUT};
+
+/// The weekly output of a resource with an efficiency of 1.0.
+const STANDARD_WEEKLY_OUTPUT: f32 = GENERIC_RESOURCE_OUTPUT * 5.0;
+
+/// TaskJuggler schedules on its own, so the project gets some room after the
+/// last task sgantt computed.
+const PROJECT_END_MARGIN_DAYS: i64 = 365;
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn task_id(task_id: u32) -> String {
+    format!("t{}", task_id)
+}
+
+fn resource_id(resource: &Resource) -> String {
+    format!("r{}", resource.id)
+}
+
+fn format_hours(hours: f32) -> String {
+    // TaskJuggler wants at most a few decimals.
+    format!("{}h", (hours * 100.0).round() / 100.0)
+}
+
+fn write_task(out: &mut String, p: &Project, task: &Task) {
+    out.push_str(&format!(
+        "task {} {} {{\n",
+        task_id(task.id),
+        quote(&task.label)
+    ));
+
+    // sgantt starts a task after its predecessors or on its earliest start,
+    // whichever is later. Only hand over the start if it is the later one.
+    let predecessors_end = task
+        .predecessors
+        .iter()
+        .filter_map(|id| p.tasks.iter().find(|x| x.id == *id))
+        .map(|x| x.get_actual_end_date(p))
+        .max();
+    if predecessors_end.is_none_or(|end| task.earliest_start_date > end) {
+        out.push_str(&format!("  start {}\n", task.earliest_start_date));
+    }
+    if !task.predecessors.is_empty() {
+        let depends: Vec<String> = task.predecessors.iter().map(|x| task_id(*x)).collect();
+        out.push_str(&format!("  depends {}\n", depends.join(", ")));
+    }
+
+    let allocations: Vec<_> = p
+        .allocations
+        .iter()
+        .filter(|x| x.taskid == task.id)
+        .map(|x| (p.get_resource_by_id(x.resourceid), x.load))
+        .collect();
+
+    if task.is_milestone() {
+        out.push_str("  milestone\n");
+    } else if allocations.is_empty() {
+        // Effort needs resources to work on it, without any we can only
+        // hand over the length sgantt computed for the planned resources.
+        out.push_str(&format!("  length {}d\n", task.get_work_days().ceil()));
+    } else {
+        out.push_str(&format!(
+            "  effort {}\n",
+            format_hours(task.duration as f32)
+        ));
+        let ids: Vec<String> = allocations
+            .iter()
+            .map(|(res, _)| resource_id(res))
+            .collect();
+        out.push_str(&format!("  allocate {}\n", ids.join(", ")));
+        for (res, load) in allocations.iter().filter(|(_, load)| *load < 1.0) {
+            out.push_str(&format!(
+                "  limits {{ dailymax {} {{ resources {} }} }}\n",
+                format_hours(load * GENERIC_RESOURCE_OUTPUT),
+                resource_id(res)
+            ));
+        }
+    }
+    out.push_str("}\n");
+}
+
+/// Exports the project as a TaskJuggler project including a task report.
+pub fn export_taskjuggler(p: &Project) -> String {
+    let mut out = String::new();
+
+    let start = p.tasks.iter().map(|x| x.get_actual_start_date(p)).min();
+    let end = p.tasks.iter().map(|x| x.get_actual_end_date(p)).max();
+    if let (Some(start), Some(end)) = (start, end) {
+        out.push_str(&format!(
+            "project sgantt \"sgantt\" {} - {} {{\n",
+            start,
+            end + Duration::days(PROJECT_END_MARGIN_DAYS)
+        ));
+    } else {
+        out.push_str("project sgantt \"sgantt\" 2023-01-01 +1y {\n");
+    }
+    out.push_str("  timeformat \"%Y-%m-%d\"\n");
+    out.push_str(&format!(
+        "  dailyworkinghours {}\n",
+        GENERIC_RESOURCE_OUTPUT
+    ));
+    out.push_str("}\n\n");
+
+    for holiday in p.holidays.iter() {
+        out.push_str(&format!("leaves holiday \"Holiday\" {}\n", holiday));
+    }
+    if !p.holidays.is_empty() {
+        out.push('\n');
+    }
+
+    for res in p.resources.iter() {
+        out.push_str(&format!(
+            "resource {} {} {{\n",
+            resource_id(res),
+            quote(&res.label)
+        ));
+        out.push_str(&format!(
+            "  efficiency {}\n",
+            res.output / STANDARD_WEEKLY_OUTPUT
+        ));
+        out.push_str("}\n");
+    }
+    out.push('\n');
+
+    for task in p.tasks.iter() {
+        write_task(&mut out, p, task);
+    }
+
+    out.push_str(
+        "\ntaskreport overview \"overview\" {\n  formats html\n  columns name, start, end, effort, resources, chart\n}\n",
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::fixtures::{allocation, project, resource, task};
+    use crate::pom::{Project, Resource};
+
+    use super::export_taskjuggler;
+
+    fn make_project() -> Project {
+        let start = date!(2023 - 06 - 01);
+        let mut p = project(
+            vec![
+                task(0, "Design \"v2\"", 80, start, vec![]),
+                task(1, "Build", 120, start, vec![0]),
+                task(2, "Release", 0, start, vec![0, 1]),
+            ],
+            vec![Resource {
+                output: 32.0,
+                ..resource(0, "Hans")
+            }],
+            vec![allocation(0, 0, 0.75)],
+        );
+        p.holidays.push(date!(2023 - 12 - 25));
+        p
+    }
+
+    #[test]
+    pub fn exports_resources_with_efficiency() {
+        let out = export_taskjuggler(&make_project());
+
+        assert!(out.starts_with("project sgantt \"sgantt\" 2023-06-01 - "));
+        assert!(out.contains("resource r0 \"Hans\" {\n  efficiency 0.8\n}\n"));
+        assert!(out.contains("leaves holiday \"Holiday\" 2023-12-25\n"));
+    }
+
+    #[test]
+    pub fn exports_tasks_with_effort_and_allocations() {
+        let out = export_taskjuggler(&make_project());
+
+        assert!(out.contains(
+            "task t0 \"Design \\\"v2\\\"\" {\n  start 2023-06-01\n  effort 80h\n  allocate r0\n  limits { dailymax 6h { resources r0 } }\n}\n"
+        ));
+        assert!(out.contains("task t1 \"Build\" {\n  depends t0\n  length 15d\n}\n"));
+        assert!(out.contains("task t2 \"Release\" {\n  depends t0, t1\n  milestone\n}\n"));
+    }
+
+    #[test]
+    pub fn keeps_a_start_later_than_the_predecessors() {
+        let mut p = make_project();
+        // Design ends in June.
+        p.tasks[1].earliest_start_date = date!(2023 - 09 - 01);
+        let out = export_taskjuggler(&p);
+
+        assert!(out.contains("task t1 \"Build\" {\n  start 2023-09-01\n  depends t0\n"));
+    }
+}
@@ -0,0 +1,513 @@
+//! Text rendering of the Gantt and load charts for terminals, drawn with
+//! Unicode block characters (or plain ASCII) and optionally ANSI colours.
+
+use time::{Date, Duration};
+
+use crate::pom::Project;
+use crate::renderer::{allocation_label, task_label};
+use crate::theme::Theme;
+use crate::typeset::{last_day, next_quarter_start, visible_links, visible_rows};
+
+pub struct TerminalOptions {
+    /// The number of columns available, including the label column.
+    pub width: usize,
+    /// Use ANSI escape sequences for colours.
+    pub color: bool,
+    /// Stick to ASCII for terminals without Unicode support.
+    pub ascii: bool,
+    /// The load bands and their colours.
+    pub theme: Theme,
+}
+
+/// The characters used for drawing, Unicode or ASCII.
+struct Glyphs {
+    bar: char,
+    /// Left aligned partial blocks from one to seven eighths, empty for ASCII.
+    eighths: &'static [char],
+    milestone: char,
+    separator: char,
+    quarter_line: char,
+    today_line: char,
+    link_down: char,
+    link_vertical: char,
+    link_corner: char,
+    link_horizontal: char,
+    /// Characters for the load bands, from idle to overloaded.
+    load: [char; 5],
+}
+
+const UNICODE: Glyphs = Glyphs {
+    bar: '█',
+    eighths: &['▏', '▎', '▍', '▌', '▋', '▊', '▉'],
+    milestone: '◆',
+    separator: '│',
+    quarter_line: '┆',
+    today_line: '┃',
+    link_down: '┐',
+    link_vertical: '│',
+    link_corner: '└',
+    link_horizontal: '─',
+    load: ['·', '░', '▒', '▓', '█'],
+};
+
+const ASCII: Glyphs = Glyphs {
+    bar: '#',
+    eighths: &[],
+    milestone: '*',
+    separator: '|',
+    quarter_line: ':',
+    today_line: '!',
+    link_down: '+',
+    link_vertical: '|',
+    link_corner: '+',
+    link_horizontal: '-',
+    load: ['.', '-', '+', '=', '#'],
+};
+
+const BAR_COLOR: &str = "#A0A0CC";
+const LINK_COLOR: &str = "#0000FF";
+const TODAY_COLOR: &str = "#FF0000";
+const GRID_COLOR: &str = "#808080";
+
+/// The days of the load chart, as in `renderer::render_resource_load_chart`.
+const LOAD_CHART_DAYS: i64 = 365;
+
+#[derive(Clone, Copy)]
+struct Cell<'a> {
+    ch: char,
+    color: Option<&'a str>,
+}
+
+const EMPTY: Cell<'static> = Cell {
+    ch: ' ',
+    color: None,
+};
+
+/// Maps days after the reference date to columns of the timeline.
+struct Scale {
+    columns: usize,
+    days_per_column: f64,
+}
+
+impl Scale {
+    fn new(days: i64, columns: usize) -> Scale {
+        Scale {
+            columns,
+            days_per_column: days.max(1) as f64 / columns as f64,
+        }
+    }
+
+    fn x(&self, reference_date: Date, date: Date) -> f64 {
+        (date - reference_date).whole_days() as f64 / self.days_per_column
+    }
+
+    fn column(&self, reference_date: Date, date: Date) -> usize {
+        (self.x(reference_date, date) as usize).min(self.columns - 1)
+    }
+}
+
+/// Parses colours like #A0A0CC. Theme colours may also be SVG colour names,
+/// which are not painted.
+fn hex_to_rgb(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#').filter(|x| x.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn paint(out: &mut String, text: &str, color: Option<&str>, options: &TerminalOptions) {
+    match color.and_then(hex_to_rgb) {
+        Some((r, g, b)) if options.color => {
+            out.push_str(&format!("\x1b[38;2;{};{};{}m{}\x1b[0m", r, g, b, text));
+        }
+        _ => out.push_str(text),
+    }
+}
+
+/// The character of the load band, spreading the theme's bands over the
+/// available characters.
+fn load_glyph(g: &Glyphs, band: usize, bands: usize) -> char {
+    g.load[band * (g.load.len() - 1) / bands.saturating_sub(1).max(1)]
+}
+
+/// Pads or cuts the text to exactly `width` characters.
+fn fit(text: &str, width: usize, options: &TerminalOptions) -> String {
+    let length = text.chars().count();
+    if length <= width {
+        return format!("{}{}", text, " ".repeat(width - length));
+    }
+    let ellipsis = if options.ascii { "~" } else { "…" };
+    let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    format!("{}{}", cut, ellipsis)
+}
+
+fn label_width(title: &str, labels: &[String], options: &TerminalOptions) -> usize {
+    let longest = labels
+        .iter()
+        .map(|x| x.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(title.chars().count());
+    longest.clamp(4, (options.width * 2 / 5).max(4))
+}
+
+fn glyphs(options: &TerminalOptions) -> &'static Glyphs {
+    if options.ascii {
+        &ASCII
+    } else {
+        &UNICODE
+    }
+}
+
+/// Writes the text into the row, as far as it fits.
+fn put_text(row: &mut [Cell], start: usize, text: &str) {
+    for (cell, ch) in row.iter_mut().skip(start).zip(text.chars()) {
+        *cell = Cell { ch, color: None };
+    }
+}
+
+/// The header row: quarter starts labelled like the SVG chart.
+fn header(scale: &Scale, reference_date: Date, last: Date) -> Vec<Cell<'static>> {
+    let mut row = vec![EMPTY; scale.columns];
+    let mut quarter_start = next_quarter_start(reference_date);
+    while quarter_start <= last {
+        put_text(
+            &mut row,
+            scale.column(reference_date, quarter_start),
+            &quarter_start.to_string(),
+        );
+        quarter_start = next_quarter_start(quarter_start);
+    }
+    row
+}
+
+/// Draws the quarter lines and the line of the reference date.
+fn draw_grid(grid: &mut [Vec<Cell>], scale: &Scale, reference_date: Date, last: Date, g: &Glyphs) {
+    let mut quarter_start = next_quarter_start(reference_date);
+    while quarter_start <= last {
+        let column = scale.column(reference_date, quarter_start);
+        for row in grid.iter_mut() {
+            row[column] = Cell {
+                ch: g.quarter_line,
+                color: Some(GRID_COLOR),
+            };
+        }
+        quarter_start = next_quarter_start(quarter_start);
+    }
+    for row in grid.iter_mut() {
+        row[0] = Cell {
+            ch: g.today_line,
+            color: Some(TODAY_COLOR),
+        };
+    }
+}
+
+/// Draws a bar from x to end_x, using partial blocks for the last column if
+/// the glyphs have them.
+fn draw_bar(row: &mut [Cell], x: f64, end_x: f64, g: &Glyphs) {
+    let columns = row.len();
+    let start = (x as usize).min(columns - 1);
+    let full_end = if g.eighths.is_empty() {
+        end_x.round() as usize
+    } else {
+        end_x as usize
+    }
+    .clamp(start, columns);
+    let bar = Cell {
+        ch: g.bar,
+        color: Some(BAR_COLOR),
+    };
+    for cell in row[start..full_end].iter_mut() {
+        *cell = bar;
+    }
+    let eighths = ((end_x - full_end as f64) * 8.0).round() as usize;
+    if !g.eighths.is_empty() && full_end < columns && eighths > 0 {
+        row[full_end] = if eighths >= 8 {
+            bar
+        } else {
+            Cell {
+                ch: g.eighths[eighths - 1],
+                color: Some(BAR_COLOR),
+            }
+        };
+    } else if full_end == start {
+        // Keep very short tasks visible.
+        row[start] = bar;
+    }
+}
+
+fn join_row(out: &mut String, label: &str, cells: &[Cell], g: &Glyphs, options: &TerminalOptions) {
+    out.push_str(label);
+    out.push(g.separator);
+    // Runs of the same colour share one escape sequence.
+    let mut run = String::new();
+    let mut run_color = None;
+    for cell in cells {
+        if cell.color != run_color && !run.is_empty() {
+            paint(out, &run, run_color, options);
+            run.clear();
+        }
+        run_color = cell.color;
+        run.push(cell.ch);
+    }
+    paint(out, run.trim_end(), run_color, options);
+    // Keep the line free of trailing blanks.
+    let trimmed = out.trim_end_matches(' ').len();
+    out.truncate(trimmed);
+    out.push('\n');
+}
+
+/// Renders the tasks that are running or upcoming at the reference date,
+/// showing the same labels, bars and links as `renderer::render_gantt`.
+pub fn render_gantt_text(p: &Project, reference_date: Date, options: &TerminalOptions) -> String {
+    let g = glyphs(options);
+    let rows = visible_rows(p, reference_date);
+    let last = last_day(&rows, reference_date);
+    let labels: Vec<String> = rows
+        .iter()
+        .map(|row| task_label(p, row.task, reference_date))
+        .collect();
+    let label_width = label_width("Task", &labels, options);
+    let columns = options.width.saturating_sub(label_width + 1).max(10);
+    let scale = Scale::new((last - reference_date).whole_days() + 1, columns);
+
+    let mut grid = vec![vec![EMPTY; columns]; rows.len()];
+    draw_grid(&mut grid, &scale, reference_date, last, g);
+
+    let row_number = |task_id: u32| rows.iter().position(|x| x.task.id == task_id).unwrap();
+    for (pred_row, row) in visible_links(&rows) {
+        let (from, to) = (row_number(pred_row.task.id), row_number(row.task.id));
+        // Right after the end of the predecessor's bar, but left of the
+        // successor so the corner points at it.
+        let start = scale.column(reference_date, row.start);
+        let column = (scale.x(reference_date, pred_row.end).ceil() as usize)
+            .min(columns - 1)
+            .min(start.saturating_sub(1));
+        let link = |ch| Cell {
+            ch,
+            color: Some(LINK_COLOR),
+        };
+        if to <= from {
+            continue;
+        }
+        grid[from][column] = link(g.link_down);
+        for cells in grid[from + 1..to].iter_mut() {
+            if cells[column].ch == ' ' || cells[column].color == Some(GRID_COLOR) {
+                cells[column] = link(g.link_vertical);
+            }
+        }
+        grid[to][column] = link(g.link_corner);
+        for cell in grid[to][column + 1..start.max(column + 1)].iter_mut() {
+            *cell = link(g.link_horizontal);
+        }
+    }
+
+    for (number, row) in rows.iter().enumerate() {
+        let cells = &mut grid[number];
+        let x = scale.x(reference_date, row.start);
+        if row.task.is_milestone() {
+            cells[scale.column(reference_date, row.start)] = Cell {
+                ch: g.milestone,
+                color: None,
+            };
+        } else {
+            let end_x = scale.x(reference_date, row.end);
+            draw_bar(cells, x, end_x, g);
+            let text_start = (end_x.ceil() as usize).min(columns) + 1;
+            put_text(cells, text_start, &allocation_label(p, row.task));
+        }
+    }
+
+    let mut out = String::new();
+    let title = fit("Task", label_width, options);
+    join_row(
+        &mut out,
+        &title,
+        &header(&scale, reference_date, last),
+        g,
+        options,
+    );
+    for (label, cells) in labels.iter().zip(grid.iter()) {
+        join_row(
+            &mut out,
+            &fit(label, label_width, options),
+            cells,
+            g,
+            options,
+        );
+    }
+    out
+}
+
+/// Renders the load of each resource for a year after the reference date.
+/// Each column shows the peak load of the days it covers relative to the
+/// resource's capacity, with the theme's bands and colours as in
+/// `renderer::render_resource_load_chart`.
+pub fn render_load_text(p: &Project, reference_date: Date, options: &TerminalOptions) -> String {
+    let g = glyphs(options);
+    let theme = &options.theme;
+    let bands = theme.load_bands.len();
+    let labels: Vec<String> = p.resources.iter().map(|x| x.label.clone()).collect();
+    let label_width = label_width("Resource", &labels, options);
+    let columns = options.width.saturating_sub(label_width + 1).max(10);
+    let scale = Scale::new(LOAD_CHART_DAYS, columns);
+    let last = reference_date + Duration::days(LOAD_CHART_DAYS - 1);
+
+    let mut out = String::new();
+    let title = fit("Resource", label_width, options);
+    join_row(
+        &mut out,
+        &title,
+        &header(&scale, reference_date, last),
+        g,
+        options,
+    );
+
+    let dates = p.task_dates();
+    for (label, res) in labels.iter().zip(p.resources.iter()) {
+        let mut peaks = vec![None::<f32>; columns];
+        for day in 0..LOAD_CHART_DAYS {
+            let date = reference_date + Duration::days(day);
+            let load = p.calculate_relative_load(&dates, res.id, date);
+            let peak = &mut peaks[scale.column(reference_date, date)];
+            *peak = Some(peak.map_or(load, |x: f32| x.max(load)));
+        }
+        let cells: Vec<Cell> = peaks
+            .iter()
+            .map(|peak| match peak {
+                Some(load) => Cell {
+                    ch: load_glyph(g, theme.load_band(*load), bands),
+                    color: Some(theme.load_color(*load)),
+                },
+                None => EMPTY,
+            })
+            .collect();
+        join_row(
+            &mut out,
+            &fit(label, label_width, options),
+            &cells,
+            g,
+            options,
+        );
+    }
+
+    let legend: Vec<String> = theme
+        .load_band_labels()
+        .iter()
+        .enumerate()
+        .map(|(band, label)| format!("{} {}", load_glyph(g, band, bands), label))
+        .collect();
+    out.push_str(&legend.join("  "));
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::fixtures::{allocation, project, resource, task};
+    use crate::pom::Project;
+    use crate::theme::{LoadBand, Theme};
+
+    use super::{render_gantt_text, render_load_text, TerminalOptions};
+
+    fn make_project() -> Project {
+        let start = date!(2023 - 06 - 08);
+        let mut p = project(
+            vec![
+                task(0, "Design", 80, start, vec![]),
+                task(1, "Build", 80, start, vec![0]),
+                task(2, "Release", 0, start, vec![1]),
+            ],
+            vec![resource(0, "Hans")],
+            vec![allocation(1, 0, 1.5)],
+        );
+        p.calculate_resource_allocations();
+        p
+    }
+
+    fn options(color: bool, ascii: bool) -> TerminalOptions {
+        TerminalOptions {
+            width: 60,
+            color,
+            ascii,
+            theme: Theme::default(),
+        }
+    }
+
+    #[test]
+    pub fn draws_bars_links_and_labels() {
+        let out = render_gantt_text(
+            &make_project(),
+            date!(2023 - 06 - 08),
+            &options(false, true),
+        );
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("Task                  |"));
+        assert!(lines[1].starts_with("Design, 9 days, 1 FTE |####"));
+        assert!(lines[1].ends_with("# FTE:0/1    :"));
+        // Build and Release start where their predecessors end, the links
+        // turn right before them.
+        assert!(lines[2].starts_with("Build, 6 days, 1 FTE  |!                 +####"));
+        assert!(lines[3].ends_with("+*"));
+        assert!(out.lines().all(|x| x.chars().count() <= 60));
+    }
+
+    #[test]
+    pub fn colors_only_when_asked() {
+        let plain = render_gantt_text(
+            &make_project(),
+            date!(2023 - 06 - 08),
+            &options(false, false),
+        );
+        let colored = render_gantt_text(
+            &make_project(),
+            date!(2023 - 06 - 08),
+            &options(true, false),
+        );
+
+        assert!(!plain.contains('\x1b'));
+        assert!(plain.contains('█'));
+        assert!(colored.contains("\x1b[38;2;160;160;204m█"));
+        assert!(colored.contains("\x1b[38;2;255;0;0m┃"));
+    }
+
+    #[test]
+    pub fn load_uses_bands_of_the_load_chart() {
+        let out = render_load_text(
+            &make_project(),
+            date!(2023 - 06 - 08),
+            &options(true, false),
+        );
+        let hans = out.lines().nth(1).unwrap();
+
+        assert!(hans.starts_with("Hans    │"));
+        // Overloaded while building, idle afterwards.
+        assert!(hans.contains("\x1b[38;2;160;0;0m█"));
+        assert!(hans.contains("\x1b[38;2;0;192;0m·"));
+    }
+
+    #[test]
+    pub fn load_uses_the_bands_of_the_theme() {
+        let mut options = options(true, true);
+        options.theme.load_bands = vec![
+            LoadBand {
+                up_to: Some(1.0),
+                color: "#0000FF".to_string(),
+            },
+            LoadBand {
+                up_to: None,
+                color: "red".to_string(),
+            },
+        ];
+        let out = render_load_text(&make_project(), date!(2023 - 06 - 08), &options);
+        let hans = out.lines().nth(1).unwrap();
+
+        // Idle in blue, overloaded in a colour the terminal cannot paint
+        assert!(hans.contains("\x1b[38;2;0;0;255m."));
+        assert!(hans.contains("\x1b[0m##\x1b"));
+        assert!(out.ends_with(". 0-100%  # over 100%\n"));
+    }
+}
@@ -0,0 +1,418 @@
+use std::{fmt, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pom::{strip_json_extensions, ProjectFormat};
+use crate::renderer::LOAD_COLORS;
+
+/// The names of the built-in themes.
+pub const PRESETS: [&str; 4] = ["light", "dark", "print", "colorblind"];
+
+/// Colours, font size and row geometry of the SVG charts.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    /// The canvas behind the chart.
+    pub background: String,
+    /// Labels and the header.
+    pub text: String,
+    pub font_size: f32,
+    pub bar_height: f32,
+    /// The space between two bars.
+    pub row_spacing: f32,
+    /// The lines of the task column, the rows and the coarsest header tier.
+    pub grid: String,
+    /// The lines of the finest header tier through the rows.
+    pub minor_grid: String,
+    pub bar_fill: String,
+    pub bar_stroke: String,
+    /// Bars, or their parts, before the reference date.
+    pub history_fill: String,
+    pub history_stroke: String,
+    /// The arrow heads of bars cut off by the window.
+    pub continuation: String,
+    /// Dependency links, dashed if the predecessor does not drive the start.
+    pub link: String,
+    /// Links on the critical path.
+    pub critical_link: String,
+    /// The line and label of the status date.
+    pub status_line: String,
+    /// The background of Saturdays and Sundays.
+    pub weekend: String,
+    /// The background of the project's holidays.
+    pub holiday: String,
+    /// The colours of the load, relative to the resource's capacity, from
+    /// idle to overloaded. Each band starts where the one before ends, the
+    /// last has no upper bound.
+    pub load_bands: Vec<LoadBand>,
+    /// Bar colours by task tag. A task's own colour comes first, then the
+    /// first rule matching one of its tags.
+    #[serde(default)]
+    pub tag_colors: Vec<TagColor>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoadBand {
+    /// The highest load in the band, 1.0 being the full capacity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up_to: Option<f32>,
+    pub color: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TagColor {
+    pub tag: String,
+    pub color: String,
+}
+
+impl Theme {
+    /// The built-in theme of the given name.
+    pub fn preset(name: &str) -> Option<Theme> {
+        match name {
+            "light" => Some(Theme::default()),
+            "dark" => Some(Theme {
+                background: "#1E1E1E".to_string(),
+                text: "#E0E0E0".to_string(),
+                grid: "#909090".to_string(),
+                minor_grid: "#404040".to_string(),
+                bar_fill: "#4A5A9A".to_string(),
+                bar_stroke: "#8C9CE0".to_string(),
+                history_fill: "#3C3C3C".to_string(),
+                history_stroke: "#686868".to_string(),
+                continuation: "#C0C0C0".to_string(),
+                link: "#6CA6FF".to_string(),
+                critical_link: "#FF6B6B".to_string(),
+                status_line: "#FF8C8C".to_string(),
+                weekend: "#2A2A2A".to_string(),
+                holiday: "#46302F".to_string(),
+                load_bands: bands(["#1F7A3A", "#3FAE49", "#C9B400", "#D64545", "#FF8080"]),
+                ..Theme::default()
+            }),
+            "print" => Some(Theme {
+                minor_grid: "#C0C0C0".to_string(),
+                bar_fill: "#B0B0B0".to_string(),
+                bar_stroke: "#404040".to_string(),
+                history_fill: "#E8E8E8".to_string(),
+                history_stroke: "#A0A0A0".to_string(),
+                continuation: "#000000".to_string(),
+                link: "#505050".to_string(),
+                critical_link: "#000000".to_string(),
+                status_line: "#000000".to_string(),
+                weekend: "#F2F2F2".to_string(),
+                holiday: "#DCDCDC".to_string(),
+                load_bands: bands(["#F0F0F0", "#C8C8C8", "#969696", "#505050", "#000000"]),
+                ..Theme::default()
+            }),
+            // The Okabe-Ito palette, told apart with all common colour vision
+            // deficiencies.
+            "colorblind" => Some(Theme {
+                bar_fill: "#56B4E9".to_string(),
+                bar_stroke: "#0072B2".to_string(),
+                link: "#0072B2".to_string(),
+                critical_link: "#D55E00".to_string(),
+                status_line: "#CC79A7".to_string(),
+                holiday: "#FAF0B4".to_string(),
+                load_bands: bands(["#0072B2", "#56B4E9", "#F0E442", "#E69F00", "#D55E00"]),
+                ..Theme::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// The height of one row, a bar plus the spacing.
+    pub fn row_height(&self) -> f32 {
+        self.bar_height + self.row_spacing
+    }
+
+    /// The height of one tier of the calendar header.
+    pub fn tier_height(&self) -> f32 {
+        self.font_size + 2.0
+    }
+
+    /// The index of the rule colouring a task with these tags.
+    pub fn tag_rule(&self, tags: &[String]) -> Option<usize> {
+        self.tag_colors
+            .iter()
+            .position(|rule| tags.contains(&rule.tag))
+    }
+
+    /// The index of the band the load falls into.
+    pub fn load_band(&self, load: f32) -> usize {
+        self.load_bands
+            .iter()
+            .position(|band| band.up_to.is_none_or(|up_to| load <= up_to))
+            .unwrap_or(self.load_bands.len() - 1)
+    }
+
+    /// The colour of the load's band.
+    pub fn load_color(&self, load: f32) -> &str {
+        &self.load_bands[self.load_band(load)].color
+    }
+
+    /// The range of each load band in percent, as in "25-50%".
+    pub fn load_band_labels(&self) -> Vec<String> {
+        let mut from = 0.0;
+        self.load_bands
+            .iter()
+            .map(|band| match band.up_to {
+                Some(up_to) => {
+                    let label = format!("{}-{}%", percent(from), percent(up_to));
+                    from = up_to;
+                    label
+                }
+                None => format!("over {}%", percent(from)),
+            })
+            .collect()
+    }
+
+    /// The baseline of a label in a row starting at y, centred on the bar.
+    pub fn label_y(&self, y: f32) -> f32 {
+        y + (self.bar_height + self.font_size) / 2.0 - 2.0
+    }
+}
+
+/// The default bounds of the load bands: quarters up to the full capacity.
+const LOAD_BOUNDS: [f32; 4] = [0.25, 0.5, 0.75, 1.0];
+
+fn bands(colors: [&str; 5]) -> Vec<LoadBand> {
+    colors
+        .iter()
+        .enumerate()
+        .map(|(i, color)| LoadBand {
+            up_to: LOAD_BOUNDS.get(i).copied(),
+            color: color.to_string(),
+        })
+        .collect()
+}
+
+fn percent(load: f32) -> f32 {
+    (load * 100.0).round()
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            background: "white".to_string(),
+            text: "black".to_string(),
+            font_size: 8.0,
+            bar_height: 8.0,
+            row_spacing: 2.0,
+            grid: "black".to_string(),
+            minor_grid: "#D0D0D0".to_string(),
+            bar_fill: "#A0A0CC".to_string(),
+            bar_stroke: "#7979CC".to_string(),
+            history_fill: "#D0D0D0".to_string(),
+            history_stroke: "#A0A0A0".to_string(),
+            continuation: "#404040".to_string(),
+            link: "blue".to_string(),
+            critical_link: "#D00000".to_string(),
+            status_line: "#FF0000".to_string(),
+            weekend: "#F0F0F0".to_string(),
+            holiday: "#FFE0E0".to_string(),
+            load_bands: bands(LOAD_COLORS),
+            tag_colors: vec![],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(std::io::Error),
+    Syntax(String),
+    UnknownPreset(String),
+    Invalid(serde_json::Error),
+    LoadBands(String),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Io(e) => write!(f, "{}", e),
+            ThemeError::Syntax(e) => write!(f, "Not a valid theme file: {}", e),
+            ThemeError::UnknownPreset(name) => write!(
+                f,
+                "There is no theme {}, the presets are {}",
+                name,
+                PRESETS.join(", ")
+            ),
+            ThemeError::Invalid(e) => write!(f, "Not a valid theme: {}", e),
+            ThemeError::LoadBands(e) => write!(f, "Not valid load bands: {}", e),
+        }
+    }
+}
+
+/// Reads a theme from a JSON, YAML or TOML document. It starts from the preset
+/// named by the key `base`, light if there is none, and overrides the keys given.
+pub fn parse_theme(content: &str, format: ProjectFormat) -> Result<Theme, ThemeError> {
+    let value: serde_json::Value = match format {
+        ProjectFormat::Json => serde_json::from_str(&strip_json_extensions(content))
+            .map_err(|e| ThemeError::Syntax(e.to_string()))?,
+        ProjectFormat::Yaml => {
+            serde_yaml::from_str(content).map_err(|e| ThemeError::Syntax(e.to_string()))?
+        }
+        ProjectFormat::Toml => {
+            toml::from_str(content).map_err(|e| ThemeError::Syntax(e.to_string()))?
+        }
+    };
+    let serde_json::Value::Object(mut overrides) = value else {
+        return Err(ThemeError::Syntax(
+            "expected a map of theme keys".to_string(),
+        ));
+    };
+
+    let base = match overrides.remove("base") {
+        Some(serde_json::Value::String(name)) => name,
+        Some(other) => return Err(ThemeError::UnknownPreset(other.to_string())),
+        None => "light".to_string(),
+    };
+    let preset = Theme::preset(&base).ok_or(ThemeError::UnknownPreset(base))?;
+    let mut merged = serde_json::to_value(preset).map_err(ThemeError::Invalid)?;
+    if let serde_json::Value::Object(fields) = &mut merged {
+        fields.extend(overrides);
+    }
+    let theme: Theme = serde_json::from_value(merged).map_err(ThemeError::Invalid)?;
+    check_load_bands(&theme.load_bands)?;
+    Ok(theme)
+}
+
+fn check_load_bands(bands: &[LoadBand]) -> Result<(), ThemeError> {
+    let Some((last, bounded)) = bands.split_last() else {
+        return Err(ThemeError::LoadBands("there are none".to_string()));
+    };
+    if last.up_to.is_some() {
+        return Err(ThemeError::LoadBands(
+            "the last band must have no up_to".to_string(),
+        ));
+    }
+    let mut from = 0.0;
+    for band in bounded {
+        match band.up_to {
+            Some(up_to) if up_to > from => from = up_to,
+            Some(up_to) => {
+                return Err(ThemeError::LoadBands(format!(
+                    "{} does not go up from {}",
+                    up_to, from
+                )))
+            }
+            None => {
+                return Err(ThemeError::LoadBands(
+                    "only the last band may have no up_to".to_string(),
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the preset of the given name, or else reads the theme file.
+pub fn load_theme(name_or_file: &str) -> Result<Theme, ThemeError> {
+    if let Some(theme) = Theme::preset(name_or_file) {
+        return Ok(theme);
+    }
+    if Path::new(name_or_file).extension().is_none() {
+        return Err(ThemeError::UnknownPreset(name_or_file.to_string()));
+    }
+    let content = fs::read_to_string(name_or_file).map_err(ThemeError::Io)?;
+    parse_theme(&content, ProjectFormat::from_file_name(name_or_file))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pom::ProjectFormat;
+
+    use super::{parse_theme, Theme, PRESETS};
+
+    #[test]
+    pub fn has_all_presets() {
+        for name in PRESETS {
+            assert!(Theme::preset(name).is_some(), "{}", name);
+        }
+        assert_eq!(Theme::preset("light"), Some(Theme::default()));
+        assert!(Theme::preset("neon").is_none());
+
+        // Greyscale only
+        let print = Theme::preset("print").unwrap();
+        for color in print
+            .load_bands
+            .iter()
+            .map(|band| &band.color)
+            .chain([&print.bar_fill, &print.link])
+        {
+            assert_eq!(color[1..3], color[3..5]);
+            assert_eq!(color[3..5], color[5..7]);
+        }
+    }
+
+    #[test]
+    pub fn overrides_a_preset_from_a_file() {
+        let theme = parse_theme(
+            "{ \"base\": \"dark\", \"font_size\": 10, /* bigger */ \"bar_fill\": \"#336699\", }",
+            ProjectFormat::Json,
+        )
+        .unwrap();
+        let dark = Theme::preset("dark").unwrap();
+        assert_eq!(theme.font_size, 10.0);
+        assert_eq!(theme.bar_fill, "#336699");
+        assert_eq!(theme.background, dark.background);
+
+        let theme = parse_theme("bar_height = 12\nrow_spacing = 4", ProjectFormat::Toml).unwrap();
+        assert_eq!(theme.row_height(), 16.0);
+        assert_eq!(theme.text, "black");
+
+        let theme = parse_theme(
+            "tag_colors:\n  - tag: backend\n    color: '#0072B2'\n  - tag: ui\n    color: orange",
+            ProjectFormat::Yaml,
+        )
+        .unwrap();
+        let tags = vec!["ui".to_string(), "backend".to_string()];
+        assert_eq!(theme.tag_rule(&tags), Some(0));
+        assert_eq!(theme.tag_rule(&tags[..1]), Some(1));
+        assert_eq!(theme.tag_rule(&[]), None);
+    }
+
+    #[test]
+    pub fn finds_the_load_band() {
+        let theme = Theme::default();
+        // The bands leave no gaps between them.
+        assert_eq!(theme.load_band(0.0), 0);
+        assert_eq!(theme.load_band(0.25), 0);
+        assert_eq!(theme.load_band(0.255), 1);
+        assert_eq!(theme.load_band(0.505), 2);
+        assert_eq!(theme.load_band(1.0), 3);
+        assert_eq!(theme.load_band(1.01), 4);
+        assert_eq!(theme.load_band(f32::INFINITY), 4);
+        assert_eq!(
+            theme.load_band_labels(),
+            ["0-25%", "25-50%", "50-75%", "75-100%", "over 100%"]
+        );
+
+        let theme = parse_theme(
+            "load_bands:\n  - up_to: 0.9\n    color: green\n  - up_to: 1.1\n    color: yellow\n  - color: red",
+            ProjectFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(theme.load_color(1.0), "yellow");
+        assert_eq!(theme.load_band_labels(), ["0-90%", "90-110%", "over 110%"]);
+    }
+
+    #[test]
+    pub fn rejects_unknown_keys_and_presets() {
+        assert!(parse_theme("bar_colour: red", ProjectFormat::Yaml).is_err());
+        assert!(parse_theme("base: neon", ProjectFormat::Yaml).is_err());
+        assert!(parse_theme("font_size: large", ProjectFormat::Yaml).is_err());
+        assert!(parse_theme("[1, 2]", ProjectFormat::Json).is_err());
+        assert!(parse_theme("load_bands: []", ProjectFormat::Yaml).is_err());
+        assert!(parse_theme(
+            "load_bands:\n  - up_to: 1.0\n    color: green\n  - up_to: 0.5\n    color: red\n  - color: black",
+            ProjectFormat::Yaml
+        )
+        .is_err());
+        assert!(parse_theme(
+            "load_bands:\n  - up_to: 1.0\n    color: green",
+            ProjectFormat::Yaml
+        )
+        .is_err());
+    }
+}
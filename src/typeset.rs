@@ -0,0 +1,352 @@
+//! Gantt charts for typeset documents: a pgfgantt `ganttchart` environment
+//! for LaTeX and a Typst snippet. Both show the same rows, labels, links and
+//! quarter calendar as `renderer::render_gantt`.
+
+use time::{Date, Duration, Month};
+
+use crate::pom::{Project, Task};
+use crate::renderer::{allocation_label, task_label};
+
+/// One task as drawn in the chart, starting no earlier than the reference date.
+pub(crate) struct Row<'a> {
+    pub task: &'a Task,
+    pub start: Date,
+    /// The day after the last day of work, equal to start for milestones.
+    pub end: Date,
+}
+
+/// Collects the tasks that are still running or upcoming at the reference date.
+pub(crate) fn visible_rows(p: &Project, reference_date: Date) -> Vec<Row<'_>> {
+    let mut rows = vec![];
+    for task in p.tasks.iter() {
+        let start = task.get_actual_start_date(p);
+        let end = task.get_actual_end_date(p);
+        if task.is_milestone() {
+            if start >= reference_date {
+                rows.push(Row {
+                    task,
+                    start,
+                    end: start,
+                });
+            }
+        } else if end > reference_date {
+            rows.push(Row {
+                task,
+                start: start.max(reference_date),
+                end,
+            });
+        }
+    }
+    rows
+}
+
+/// The last day shown in the chart.
+pub(crate) fn last_day(rows: &[Row], reference_date: Date) -> Date {
+    rows.iter()
+        .map(|row| {
+            if row.task.is_milestone() {
+                row.start
+            } else {
+                row.end - Duration::days(1)
+            }
+        })
+        .max()
+        .unwrap_or(reference_date)
+        .max(reference_date)
+}
+
+pub(crate) fn next_quarter_start(date: Date) -> Date {
+    let quarter_start_month = (date.month() as u8 - 1) / 3 * 3 + 1;
+    if quarter_start_month == 10 {
+        Date::from_calendar_date(date.year() + 1, Month::January, 1).unwrap()
+    } else {
+        let month = Month::try_from(quarter_start_month + 3).unwrap();
+        Date::from_calendar_date(date.year(), month, 1).unwrap()
+    }
+}
+
+/// Splits the days from first to last (inclusive) at the start of each
+/// quarter, returning the first day and the number of days of each part.
+fn quarter_segments(first: Date, last: Date) -> Vec<(Date, i64)> {
+    let mut segments = vec![];
+    let mut segment_start = first;
+    while segment_start <= last {
+        let segment_end = next_quarter_start(segment_start).min(last + Duration::days(1));
+        segments.push((segment_start, (segment_end - segment_start).whole_days()));
+        segment_start = segment_end;
+    }
+    segments
+}
+
+fn row_name(task: &Task) -> String {
+    format!("t{}", task.id)
+}
+
+/// The links between rows that are both visible.
+pub(crate) fn visible_links<'a>(rows: &'a [Row<'a>]) -> Vec<(&'a Row<'a>, &'a Row<'a>)> {
+    let mut links = vec![];
+    for row in rows.iter() {
+        for pred_id in row.task.predecessors.iter() {
+            if let Some(pred_row) = rows.iter().find(|x| x.task.id == *pred_id) {
+                links.push((pred_row, row));
+            }
+        }
+    }
+    links
+}
+
+fn escape_latex(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Renders a pgfgantt `ganttchart` environment, needs `\usepackage{pgfgantt}`.
+pub fn render_pgfgantt(p: &Project, reference_date: Date) -> String {
+    let rows = visible_rows(p, reference_date);
+    let last = last_day(&rows, reference_date);
+
+    let mut out = String::from("% Requires \\usepackage{pgfgantt}\n");
+    out.push_str("\\begin{ganttchart}[\n");
+    out.push_str("    time slot format=isodate,\n");
+    out.push_str("    x unit=0.4mm,\n");
+    out.push_str("    y unit title=4mm,\n");
+    out.push_str("    y unit chart=5mm,\n");
+    out.push_str("    hgrid,\n");
+    out.push_str("    title label font=\\tiny,\n");
+    out.push_str("    bar label font=\\tiny,\n");
+    out.push_str("    milestone label font=\\tiny,\n");
+    out.push_str("    bar/.append style={fill={rgb,255:red,160;green,160;blue,204}, draw={rgb,255:red,121;green,121;blue,204}},\n");
+    out.push_str("    link/.style={-latex, draw=blue},\n");
+    out.push_str(&format!("  ]{{{}}}{{{}}}\n", reference_date, last));
+
+    let titles: Vec<String> = quarter_segments(reference_date, last)
+        .iter()
+        .map(|(start, days)| format!("\\gantttitle{{{}}}{{{}}}", start, days))
+        .collect();
+    out.push_str(&format!("  {} \\\\\n", titles.join(" ")));
+    out.push_str("  \\gantttitlecalendar{month=shortname} \\\\\n");
+
+    for (number, row) in rows.iter().enumerate() {
+        let line_end = if number + 1 < rows.len() { " \\\\" } else { "" };
+        let label = escape_latex(&task_label(p, row.task, reference_date));
+        if row.task.is_milestone() {
+            out.push_str(&format!(
+                "  \\ganttmilestone[name={}]{{{}}}{{{}}}{}\n",
+                row_name(row.task),
+                label,
+                row.start,
+                line_end
+            ));
+        } else {
+            out.push_str(&format!(
+                "  \\ganttbar[name={}]{{{}}}{{{}}}{{{}}}{}\n",
+                row_name(row.task),
+                label,
+                row.start,
+                row.end - Duration::days(1),
+                line_end
+            ));
+        }
+    }
+
+    for (pred_row, row) in visible_links(&rows) {
+        out.push_str(&format!(
+            "  \\ganttlink{{{}}}{{{}}}\n",
+            row_name(pred_row.task),
+            row_name(row.task)
+        ));
+    }
+
+    for row in rows.iter().filter(|x| !x.task.is_milestone()) {
+        out.push_str(&format!(
+            "  \\node[anchor=west, font=\\tiny] at ({}.east) {{{}}};\n",
+            row_name(row.task),
+            escape_latex(&allocation_label(p, row.task))
+        ));
+    }
+
+    out.push_str("\\end{ganttchart}\n");
+    out
+}
+
+fn typst_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+const TYPST_LABEL_WIDTH_MM: f32 = 60.0;
+const TYPST_DAY_WIDTH_MM: f32 = 0.4;
+const TYPST_ROW_HEIGHT_MM: f32 = 5.0;
+const TYPST_BAR_HEIGHT_MM: f32 = 3.0;
+const TYPST_TITLE_HEIGHT_MM: f32 = 5.0;
+
+fn typst_x(reference_date: Date, date: Date) -> f32 {
+    TYPST_LABEL_WIDTH_MM + (date - reference_date).whole_days() as f32 * TYPST_DAY_WIDTH_MM
+}
+
+fn typst_row_y(number: usize) -> f32 {
+    TYPST_TITLE_HEIGHT_MM + number as f32 * TYPST_ROW_HEIGHT_MM
+}
+
+/// Renders a Typst snippet drawing the chart with built-in shapes only.
+pub fn render_typst(p: &Project, reference_date: Date) -> String {
+    let rows = visible_rows(p, reference_date);
+    let last = last_day(&rows, reference_date);
+    let width = typst_x(reference_date, last + Duration::days(1));
+    let height = typst_row_y(rows.len());
+
+    let mut out = String::from("// Gantt chart generated by sgantt\n");
+    out.push_str(&format!(
+        "#block(width: {:.2}mm, height: {:.2}mm, {{\n",
+        width, height
+    ));
+    out.push_str("  set text(size: 6pt)\n");
+    out.push_str(&format!(
+        "  place(dx: 0mm, dy: 0mm, {})\n",
+        typst_string("Task")
+    ));
+
+    for (start, _) in quarter_segments(reference_date, last) {
+        let x = typst_x(reference_date, start);
+        out.push_str(&format!(
+            "  place(dx: {:.2}mm, dy: 0mm, line(length: {:.2}mm, angle: 90deg, stroke: 0.5pt))\n",
+            x, height
+        ));
+        out.push_str(&format!(
+            "  place(dx: {:.2}mm, dy: 0mm, {})\n",
+            x + 0.5,
+            typst_string(&start.to_string())
+        ));
+    }
+
+    for (number, row) in rows.iter().enumerate() {
+        let y = typst_row_y(number);
+        out.push_str(&format!(
+            "  place(dx: 0mm, dy: {:.2}mm, line(length: {:.2}mm, stroke: 0.3pt))\n",
+            y, width
+        ));
+        out.push_str(&format!(
+            "  place(dx: 0mm, dy: {:.2}mm, {})\n",
+            y + 1.0,
+            typst_string(&task_label(p, row.task, reference_date))
+        ));
+        let x = typst_x(reference_date, row.start);
+        if row.task.is_milestone() {
+            out.push_str(&format!(
+                "  place(dx: {:.2}mm, dy: {:.2}mm, rotate(45deg, square(size: {:.2}mm, fill: black)))\n",
+                x - TYPST_BAR_HEIGHT_MM / 2.0,
+                y + 1.0,
+                TYPST_BAR_HEIGHT_MM
+            ));
+        } else {
+            let end_x = typst_x(reference_date, row.end);
+            out.push_str(&format!(
+                "  place(dx: {:.2}mm, dy: {:.2}mm, rect(width: {:.2}mm, height: {:.2}mm, fill: rgb(\"#A0A0CC\"), stroke: rgb(\"#7979CC\")))\n",
+                x,
+                y + 1.0,
+                end_x - x,
+                TYPST_BAR_HEIGHT_MM
+            ));
+            out.push_str(&format!(
+                "  place(dx: {:.2}mm, dy: {:.2}mm, {})\n",
+                end_x + 1.0,
+                y + 1.0,
+                typst_string(&allocation_label(p, row.task))
+            ));
+        }
+    }
+
+    let row_number = |row: &Row| rows.iter().position(|x| x.task.id == row.task.id).unwrap();
+    for (pred_row, row) in visible_links(&rows) {
+        let from_y = typst_row_y(row_number(pred_row)) + 1.0 + TYPST_BAR_HEIGHT_MM / 2.0;
+        let to_y = typst_row_y(row_number(row)) + 1.0 + TYPST_BAR_HEIGHT_MM / 2.0;
+        out.push_str(&format!(
+            "  place(line(start: ({:.2}mm, {:.2}mm), end: ({:.2}mm, {:.2}mm), stroke: blue + 0.5pt))\n",
+            typst_x(reference_date, pred_row.end),
+            from_y,
+            typst_x(reference_date, row.start),
+            to_y
+        ));
+    }
+
+    out.push_str("})\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use crate::pom::fixtures::{project, task};
+    use crate::pom::Project;
+
+    use super::{quarter_segments, render_pgfgantt, render_typst};
+
+    fn make_project() -> Project {
+        let start = date!(2023 - 06 - 01);
+        project(
+            vec![
+                task(0, "Design & test", 80, start, vec![]),
+                task(1, "Build", 40, start, vec![0]),
+                task(2, "Release", 0, start, vec![1]),
+                task(3, "Kick-off", 0, start, vec![]),
+            ],
+            vec![],
+            vec![],
+        )
+    }
+
+    #[test]
+    pub fn splits_at_quarters() {
+        let segments = quarter_segments(date!(2023 - 06 - 08), date!(2023 - 10 - 02));
+
+        assert_eq!(
+            segments,
+            vec![
+                (date!(2023 - 06 - 08), 23),
+                (date!(2023 - 07 - 01), 92),
+                (date!(2023 - 10 - 01), 2)
+            ]
+        );
+    }
+
+    #[test]
+    pub fn pgfgantt_has_bars_milestones_and_links() {
+        let out = render_pgfgantt(&make_project(), date!(2023 - 06 - 08));
+
+        assert!(out.contains("]{2023-06-08}{2023-06-22}\n"));
+        assert!(out.contains("\\gantttitle{2023-06-08}{15} \\\\\n"));
+        // Design started before the reference date and is cut off there.
+        assert!(out.contains(
+            "\\ganttbar[name=t0]{Design \\& test, 4 days, 1 FTE}{2023-06-08}{2023-06-14} \\\\\n"
+        ));
+        assert!(out.contains("\\ganttmilestone[name=t2]{Release, 0 days, 1 FTE}{2023-06-22}\n"));
+        assert!(out.contains("\\ganttlink{t0}{t1}\n  \\ganttlink{t1}{t2}\n"));
+        assert!(out.contains("at (t1.east) {FTE:0/1};"));
+        // The kick-off is in the past.
+        assert!(!out.contains("Kick-off"));
+    }
+
+    #[test]
+    pub fn typst_has_bars_milestones_and_links() {
+        let out = render_typst(&make_project(), date!(2023 - 06 - 08));
+
+        assert!(out.starts_with("// Gantt chart generated by sgantt\n#block("));
+        assert_eq!(out.matches("rect(").count(), 2);
+        assert_eq!(out.matches("rotate(45deg").count(), 1);
+        assert_eq!(out.matches("stroke: blue").count(), 2);
+        assert!(out.contains("\"Design & test, 4 days, 1 FTE\""));
+        assert!(!out.contains("Kick-off"));
+    }
+}